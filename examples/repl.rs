@@ -1,49 +1,98 @@
 use std::io::{self, Write};
+use std::rc::Rc;
 
-use scarab::compiler::compile;
-use scarab::parser::parse_implicit_form_list;
+use scarab::compiler;
+use scarab::parser::{parse_implicit_form_list, PositionMap};
+use scarab::value::Value;
 use scarab::vm::Vm;
 
-macro_rules! try_or_bail {
-    ($expr:expr, $msg_prefix:expr $(,)?) => {
-        match $expr {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("{}: {}", $msg_prefix, e);
-                return;
-            }
-        }
-    };
+/// Forwards every write straight to stdout, so the `debug` builtin's output appears immediately
+/// instead of needing to be drained from a buffer between lines.
+struct StdoutWriter;
+
+impl core::fmt::Write for StdoutWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        print!("{}", s);
+
+        Ok(())
+    }
 }
 
-pub fn run_line(code: &str, output: &mut impl Write) {
-    let (program, positions) =
-        try_or_bail!(parse_implicit_form_list(code.chars()), "parsing failed",);
+/// One REPL session: a [`compiler::Session`] and a [`Vm`] that both live for as long as the
+/// program runs, so variables, user-defined functions and register contents set by one line
+/// stay visible to the next instead of being thrown away.
+struct Session<'a> {
+    compiler: compiler::Session,
+    vm: Vm<'a>,
+}
+
+impl<'a> Session<'a> {
+    fn new(debug_output: &'a mut impl core::fmt::Write) -> Self {
+        Self {
+            compiler: compiler::Session::new(),
+            vm: Vm::new(debug_output),
+        }
+    }
+
+    /// Compiles and runs one already-parsed top-level form against this session's accumulated
+    /// state, printing the value of its last statement (if it had one) with `Value`'s `Display`.
+    fn run(&mut self, program: Rc<Value>, positions: PositionMap) {
+        let (instructions, argument_positions, last_reg) =
+            match self.compiler.compile_line(program, positions) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("compilation failed: {}", e);
+                    return;
+                }
+            };
 
-    let instructions = try_or_bail!(compile(program, positions), "compilation failed");
+        self.vm
+            .load_with_positions(instructions, argument_positions);
+        if let Err(e) = self.vm.run() {
+            eprintln!("running failed: {}", e);
+            return;
+        }
 
-    let mut vm = Vm::new(output);
-    vm.load(instructions);
-    try_or_bail!(vm.run(), "running failed");
+        if let Some(reg) = last_reg {
+            println!("{}", self.vm.register(reg));
+        }
+    }
 }
 
 fn main() {
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
-    let mut line = String::new();
-    print!("> ");
-    stdout.flush().unwrap();
-    while let Ok(..) = stdin.read_line(&mut line) {
-        if line == "" {
+    let mut debug_output = StdoutWriter;
+    let mut session = Session::new(&mut debug_output);
+
+    let mut buffer = String::new();
+    let mut prompt = "> ";
+    loop {
+        print!("{}", prompt);
+        stdout.flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.read_line(&mut line).unwrap_or(0) == 0 {
             println!();
             break;
         }
+        buffer.push_str(&line);
 
-        run_line(&line, &mut stdout);
-
-        line.clear();
-        print!("> ");
-        stdout.flush().unwrap();
+        match parse_implicit_form_list(buffer.chars()) {
+            Ok((program, positions)) => {
+                session.run(program, positions);
+                buffer.clear();
+                prompt = "> ";
+            }
+            Err(e) if e.is_incomplete() => {
+                prompt = "... ";
+            }
+            Err(e) => {
+                eprintln!("parsing failed: {}", e);
+                buffer.clear();
+                prompt = "> ";
+            }
+        }
     }
 }
@@ -20,10 +20,30 @@
 //! boilerplate.
 #![feature(try_trait_v2)]
 
+use std::collections::VecDeque;
+
 use thiserror::Error;
 
 pub type Position = (usize, usize);
 
+/// A source range: a construct's first position through one past its last, the same half-open
+/// convention as a slice. Every [`ResultAt`] is labeled with one of these rather than a bare
+/// [`Position`] so that a multi-character token (or anything built from one) can report its full
+/// extent, not just where it starts.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Span {
+    /// A zero-width span at a single point, for callers (like end-of-input reporting) with no real
+    /// range to cover.
+    pub fn point(at: Position) -> Span {
+        Span { start: at, end: at }
+    }
+}
+
 /// The `Source` trait allows for reading items with position information.
 ///
 /// Can be used directly with [`next()`](Self::next), but [`reader()`](Self::reader) allows use of many utility methods.
@@ -36,7 +56,100 @@ pub trait Source: Sized {
     fn reader(self) -> Reader<Self> {
         Reader {
             reader: self,
-            peeked: None,
+            peeked: VecDeque::new(),
+        }
+    }
+
+    /// Transforms every successfully-read item, leaving `ErrAt`/`NoneAt` untouched.
+    fn map<O, F: FnMut(Self::Output) -> O>(self, op: F) -> Map<Self, F> {
+        Map { source: self, op }
+    }
+
+    /// Transforms the error of a failed read, leaving `OkAt`/`NoneAt` untouched.
+    fn map_err<O, F: FnMut(Self::Error) -> O>(self, op: F) -> MapErr<Self, F> {
+        MapErr { source: self, op }
+    }
+
+    /// Skips successfully-read items that don't match `predicate`, leaving `ErrAt`/`NoneAt`
+    /// untouched.
+    fn filter<F: FnMut(&Self::Output) -> bool>(self, predicate: F) -> Filter<Self, F> {
+        Filter {
+            source: self,
+            predicate,
+        }
+    }
+
+    /// Like [`map`](Self::map), but `op` can reject an item by returning `None`, in which case the
+    /// next upstream item is tried instead.
+    fn filter_map<O, F: FnMut(Self::Output) -> Option<O>>(self, op: F) -> FilterMap<Self, F> {
+        FilterMap { source: self, op }
+    }
+
+    /// Like [`map`](Self::map), but `op` can fail a successfully-read item, turning it into an
+    /// `ErrAt` at the same position.
+    fn and_then<O, F: FnMut(Self::Output) -> Result<O, Self::Error>>(
+        self,
+        op: F,
+    ) -> AndThen<Self, F> {
+        AndThen { source: self, op }
+    }
+
+    /// Yields only the first `n` items, then reports a clean `NoneAt` at the position just past
+    /// the last one yielded (or the origin, if `n` is `0`).
+    fn take(self, n: usize) -> Take<Self> {
+        Take {
+            source: self,
+            remaining: n,
+            at: Span::point((1, 1)),
+        }
+    }
+
+    /// Discards the first `n` items before yielding anything, propagating an `ErrAt`/`NoneAt` hit
+    /// during the skip itself.
+    fn skip(self, n: usize) -> Skip<Self> {
+        Skip {
+            source: self,
+            remaining: n,
+        }
+    }
+
+    /// Yields everything from this source, then, once it reports `NoneAt`, switches over and
+    /// yields everything from `other` — useful for concatenating inputs (a prelude plus a user
+    /// file). An `ErrAt` from either side is passed through immediately, and `NoneAt` is only
+    /// reported once both sources are exhausted.
+    fn chain<S2: Source<Output = Self::Output, Error = Self::Error>>(
+        self,
+        other: S2,
+    ) -> Chain<Self, S2> {
+        Chain {
+            first: self,
+            second: other,
+            on_second: false,
+        }
+    }
+
+    /// Runs this source and `other` in lockstep, yielding `(Self::Output, S2::Output)` only when
+    /// both succeed; an `ErrAt`/`NoneAt` from either side is surfaced otherwise. Each zipped item
+    /// is reported at this source's position — use [`zip_at`](Self::zip_at) to combine positions
+    /// differently, since the two sources have their own, unrelated `(line, column)` spaces.
+    fn zip<S2: Source<Error = Self::Error>>(
+        self,
+        other: S2,
+    ) -> Zip<Self, S2, fn(Span, Span) -> Span> {
+        self.zip_at(other, |first, _second| first)
+    }
+
+    /// Like [`zip`](Self::zip), but `combine` picks the reported position for each successfully
+    /// zipped item from the two sources' individual positions.
+    fn zip_at<S2: Source<Error = Self::Error>, F: FnMut(Span, Span) -> Span>(
+        self,
+        other: S2,
+        combine: F,
+    ) -> Zip<Self, S2, F> {
+        Zip {
+            first: self,
+            second: other,
+            combine,
         }
     }
 }
@@ -46,6 +159,7 @@ pub struct CharSource<I: Iterator<Item = char>> {
     input: I,
     line: usize,
     column: usize,
+    byte_pos: usize,
 }
 
 impl<I: Iterator<Item = char>> CharSource<I> {
@@ -54,8 +168,15 @@ impl<I: Iterator<Item = char>> CharSource<I> {
             input,
             line: 1,
             column: 1,
+            byte_pos: 0,
         }
     }
+
+    /// The number of bytes consumed so far, for callers (such as a `&str`-backed tokenizer) that
+    /// want to slice back into the original buffer rather than rebuild it character by character.
+    pub fn byte_pos(&self) -> usize {
+        self.byte_pos
+    }
 }
 
 /// Errors produced by a [CharSource].
@@ -71,9 +192,9 @@ impl<I: Iterator<Item = char>> Source for CharSource<I> {
 
     fn next(&mut self) -> ResultAt<char, Self::Error> {
         match self.input.next() {
-            None => NoneAt((self.line, self.column)),
+            None => NoneAt(Span::point((self.line, self.column))),
             Some(c) => {
-                let result = OkAt(c, (self.line, self.column));
+                let start = (self.line, self.column);
 
                 if c == '\n' {
                     self.line += 1;
@@ -81,8 +202,15 @@ impl<I: Iterator<Item = char>> Source for CharSource<I> {
                 } else {
                     self.column += 1;
                 }
-
-                result
+                self.byte_pos += c.len_utf8();
+
+                OkAt(
+                    c,
+                    Span {
+                        start,
+                        end: (self.line, self.column),
+                    },
+                )
             }
         }
     }
@@ -100,26 +228,26 @@ impl<I: Iterator<Item = char>> Source for CharSource<I> {
 /// let mut once = false;
 /// let mut reader = source_from_fn(|| -> ResultAt<_, Infallible> {
 ///     if once {
-///         NoneAt((1, 2))
+///         NoneAt(Span::point((1, 2)))
 ///     } else {
 ///         once = true;
-///         OkAt(true, (1, 1))
+///         OkAt(true, Span::point((1, 1)))
 ///     }
 /// }).reader();
-/// assert_eq!(reader.next(), OkAt(true, (1, 1)));
-/// assert_eq!(reader.next(), NoneAt((1, 2)));
+/// assert_eq!(reader.next(), OkAt(true, Span::point((1, 1))));
+/// assert_eq!(reader.next(), NoneAt(Span::point((1, 2))));
 ///
 /// let mut once = false;
 /// let mut reader = source_from_fn(|| {
 ///     if once {
-///         ErrAt("failed!", (1, 2))
+///         ErrAt("failed!", Span::point((1, 2)))
 ///     } else {
 ///         once = true;
-///         OkAt(true, (1, 1))
+///         OkAt(true, Span::point((1, 1)))
 ///     }
 /// }).reader();
-/// assert_eq!(reader.next(), OkAt(true, (1, 1)));
-/// assert_eq!(reader.next(), ErrAt("failed!", (1, 2)));
+/// assert_eq!(reader.next(), OkAt(true, Span::point((1, 1))));
+/// assert_eq!(reader.next(), ErrAt("failed!", Span::point((1, 2))));
 /// ```
 pub fn source_from_fn<T, E>(
     next_op: impl FnMut() -> ResultAt<T, E>,
@@ -140,18 +268,305 @@ impl<T, E, O: FnMut() -> ResultAt<T, E>> Source for SourceFromFn<T, E, O> {
     }
 }
 
+/// A [`Source`] that applies a function to every successfully-read item, from [`Source::map`].
+pub struct Map<S, F> {
+    source: S,
+    op: F,
+}
+
+impl<S: Source, O, F: FnMut(S::Output) -> O> Source for Map<S, F> {
+    type Output = O;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        self.source.next().map(&mut self.op)
+    }
+}
+
+/// A [`Source`] that transforms a failed read's error, from [`Source::map_err`].
+pub struct MapErr<S, F> {
+    source: S,
+    op: F,
+}
+
+impl<S: Source, O, F: FnMut(S::Error) -> O> Source for MapErr<S, F> {
+    type Output = S::Output;
+    type Error = O;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        self.source.next().map_err(&mut self.op)
+    }
+}
+
+/// A [`Source`] that skips items not matching a predicate, from [`Source::filter`].
+pub struct Filter<S, F> {
+    source: S,
+    predicate: F,
+}
+
+impl<S: Source, F: FnMut(&S::Output) -> bool> Source for Filter<S, F> {
+    type Output = S::Output;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        loop {
+            match self.source.next() {
+                OkAt(x, at) => {
+                    if (self.predicate)(&x) {
+                        return OkAt(x, at);
+                    }
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A [`Source`] that maps and filters in one pass, from [`Source::filter_map`].
+pub struct FilterMap<S, F> {
+    source: S,
+    op: F,
+}
+
+impl<S: Source, O, F: FnMut(S::Output) -> Option<O>> Source for FilterMap<S, F> {
+    type Output = O;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        loop {
+            match self.source.next() {
+                OkAt(x, at) => {
+                    if let Some(o) = (self.op)(x) {
+                        return OkAt(o, at);
+                    }
+                }
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return NoneAt(at),
+            }
+        }
+    }
+}
+
+/// A [`Source`] that can fail a successfully-read item, from [`Source::and_then`].
+pub struct AndThen<S, F> {
+    source: S,
+    op: F,
+}
+
+impl<S: Source, O, F: FnMut(S::Output) -> Result<O, S::Error>> Source for AndThen<S, F> {
+    type Output = O;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        self.source.next().and_then(&mut self.op)
+    }
+}
+
+/// A [`Source`] that yields only the first `n` items, from [`Source::take`].
+pub struct Take<S> {
+    source: S,
+    remaining: usize,
+    // The position just past the last item yielded, for reporting where the truncated `NoneAt`
+    // belongs; stays at the origin if no item is ever yielded.
+    at: Span,
+}
+
+impl<S: Source> Source for Take<S> {
+    type Output = S::Output;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        if self.remaining == 0 {
+            return NoneAt(Span::point(self.at.end));
+        }
+
+        self.remaining -= 1;
+
+        let result = self.source.next();
+
+        if let OkAt(_, at) = result {
+            self.at = at;
+        }
+
+        result
+    }
+}
+
+/// A [`Source`] that discards the first `n` items, from [`Source::skip`].
+pub struct Skip<S> {
+    source: S,
+    remaining: usize,
+}
+
+impl<S: Source> Source for Skip<S> {
+    type Output = S::Output;
+    type Error = S::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        while self.remaining > 0 {
+            match self.source.next() {
+                OkAt(_, _) => {
+                    self.remaining -= 1;
+                }
+                result => return result,
+            }
+        }
+
+        self.source.next()
+    }
+}
+
+/// A [`Source`] that yields from `first`, then switches to `second`, from [`Source::chain`].
+pub struct Chain<S1, S2> {
+    first: S1,
+    second: S2,
+    on_second: bool,
+}
+
+impl<S1: Source, S2: Source<Output = S1::Output, Error = S1::Error>> Source for Chain<S1, S2> {
+    type Output = S1::Output;
+    type Error = S1::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        if !self.on_second {
+            match self.first.next() {
+                NoneAt(_) => {
+                    self.on_second = true;
+                }
+                result => return result,
+            }
+        }
+
+        self.second.next()
+    }
+}
+
+/// A [`Source`] that runs two sources in lockstep, from [`Source::zip`]/[`Source::zip_at`].
+pub struct Zip<S1, S2, F> {
+    first: S1,
+    second: S2,
+    combine: F,
+}
+
+impl<S1: Source, S2: Source<Error = S1::Error>, F: FnMut(Span, Span) -> Span> Source
+    for Zip<S1, S2, F>
+{
+    type Output = (S1::Output, S2::Output);
+    type Error = S1::Error;
+
+    fn next(&mut self) -> ResultAt<Self::Output, Self::Error> {
+        match self.first.next() {
+            OkAt(a, at1) => match self.second.next() {
+                OkAt(b, at2) => OkAt((a, b), (self.combine)(at1, at2)),
+                ErrAt(e, at) => ErrAt(e, at),
+                NoneAt(at) => NoneAt(at),
+            },
+            ErrAt(e, at) => {
+                self.second.next();
+                ErrAt(e, at)
+            }
+            NoneAt(at) => {
+                self.second.next();
+                NoneAt(at)
+            }
+        }
+    }
+}
+
+/// Create a [`Source`] from an [`Iterator`] of [`Result`]s, tracking a simple incrementing
+/// position for each item.
+///
+/// Bridges the ubiquitous "iterator of `Result`s" pattern (`io::Lines`, serde streams, etc.) into
+/// this crate's position-aware [`ResultAt`] world, without requiring a hand-rolled [`Source`]
+/// impl. [`results_from_source`] is the inverse conversion.
+///
+/// # Examples
+///
+/// ```
+/// # use result_at::*;
+/// let mut reader = source_from_results(vec![Ok(1), Ok(2), Err("oh no")].into_iter()).reader();
+/// assert_eq!(reader.next(), OkAt(1, Span::point((0, 1))));
+/// assert_eq!(reader.next(), OkAt(2, Span::point((0, 2))));
+/// assert_eq!(reader.next(), ErrAt("oh no", Span::point((0, 3))));
+/// ```
+pub fn source_from_results<T, E>(
+    iter: impl Iterator<Item = Result<T, E>>,
+) -> impl Source<Output = T, Error = E> {
+    SourceFromResults { iter, pos: 0 }
+}
+
+struct SourceFromResults<T, E, I: Iterator<Item = Result<T, E>>> {
+    iter: I,
+    pos: usize,
+}
+
+impl<T, E, I: Iterator<Item = Result<T, E>>> Source for SourceFromResults<T, E, I> {
+    type Output = T;
+    type Error = E;
+
+    fn next(&mut self) -> ResultAt<T, E> {
+        self.pos += 1;
+        let at = Span::point((0, self.pos));
+
+        match self.iter.next() {
+            Some(Ok(x)) => OkAt(x, at),
+            Some(Err(e)) => ErrAt(e, at),
+            None => NoneAt(at),
+        }
+    }
+}
+
+/// Converts a [`Source`] into a plain [`Iterator`] of [`Result`]s, discarding position
+/// information and stopping at the first `ErrAt` or `NoneAt`; the inverse of
+/// [`source_from_results`].
+pub fn results_from_source<S: Source>(
+    source: S,
+) -> impl Iterator<Item = Result<S::Output, S::Error>> {
+    ResultsFromSource {
+        reader: source.reader(),
+        stopped: false,
+    }
+}
+
+struct ResultsFromSource<S: Source> {
+    reader: Reader<S>,
+    stopped: bool,
+}
+
+impl<S: Source> Iterator for ResultsFromSource<S> {
+    type Item = Result<S::Output, S::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+
+        match self.reader.next() {
+            OkAt(x, _) => Some(Ok(x)),
+            ErrAt(e, _) => {
+                self.stopped = true;
+                Some(Err(e))
+            }
+            NoneAt(_) => {
+                self.stopped = true;
+                None
+            }
+        }
+    }
+}
+
 /// Utility wrapper around a [`Source`].
 pub struct Reader<S: Source> {
     reader: S,
-    peeked: Option<ResultAt<S::Output, S::Error>>,
+    peeked: VecDeque<ResultAt<S::Output, S::Error>>,
 }
 
 impl<S: Source> Reader<S> {
     /// Fetch the next result from the source.
     ///
-    /// Will take the last peeked item, if any.
+    /// Will take the front of the lookahead buffer, if anything has been peeked.
     pub fn next(&mut self) -> ResultAt<S::Output, S::Error> {
-        if let Some(x) = self.peeked.take() {
+        if let Some(x) = self.peeked.pop_front() {
             return x;
         }
 
@@ -160,9 +575,26 @@ impl<S: Source> Reader<S> {
 
     /// Peek at the next item without consuming it.
     pub fn peek(&mut self) -> &ResultAt<S::Output, S::Error> {
-        let reader = &mut self.reader;
+        self.peek_nth(0)
+    }
 
-        self.peeked.get_or_insert_with(|| reader.next())
+    /// Peek `n` items ahead without consuming anything, filling the lookahead buffer from the
+    /// source as needed.
+    ///
+    /// Once a terminal `ErrAt`/`NoneAt` has been buffered, it's returned for that index and every
+    /// deeper one instead of pulling the (already-exhausted or failed) source any further.
+    pub fn peek_nth(&mut self, n: usize) -> &ResultAt<S::Output, S::Error> {
+        while self.peeked.len() <= n {
+            if matches!(self.peeked.back(), Some(ErrAt(_, _) | NoneAt(_))) {
+                break;
+            }
+
+            let next = self.reader.next();
+            self.peeked.push_back(next);
+        }
+
+        let last = self.peeked.len() - 1;
+        &self.peeked[n.min(last)]
     }
 
     /// Returns an [`Iterator`] that yields all items up to the first error or `NoneAt`.
@@ -222,16 +654,161 @@ impl<S: Source> Reader<S> {
             NoneAt(_) => None,
         })
     }
+
+    /// Counts the remaining items, stopping at the first `ErrAt` instead of looping forever or
+    /// over-counting on a transient error.
+    pub fn count(&mut self) -> ResultAt<usize, S::Error> {
+        self.fold(0, |count, _| count + 1)
+    }
+
+    /// Folds the remaining items into a single value, short-circuiting on the first `ErrAt`.
+    pub fn fold<B>(
+        &mut self,
+        init: B,
+        mut f: impl FnMut(B, S::Output) -> B,
+    ) -> ResultAt<B, S::Error> {
+        let mut acc = init;
+
+        loop {
+            match self.next() {
+                OkAt(x, _) => acc = f(acc, x),
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return OkAt(acc, at),
+            }
+        }
+    }
+
+    /// Like [`fold`](Self::fold), but `f` can itself fail, which also short-circuits the fold.
+    pub fn try_fold<B>(
+        &mut self,
+        init: B,
+        mut f: impl FnMut(B, S::Output) -> Result<B, S::Error>,
+    ) -> ResultAt<B, S::Error> {
+        let mut acc = init;
+
+        loop {
+            match self.next() {
+                OkAt(x, at) => match f(acc, x) {
+                    Ok(next_acc) => acc = next_acc,
+                    Err(e) => return ErrAt(e, at),
+                },
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return OkAt(acc, at),
+            }
+        }
+    }
+
+    /// Returns whether every remaining item matches `predicate`, stopping as soon as one doesn't.
+    pub fn all(
+        &mut self,
+        mut predicate: impl FnMut(S::Output) -> bool,
+    ) -> ResultAt<bool, S::Error> {
+        loop {
+            match self.next() {
+                OkAt(x, at) => {
+                    if !predicate(x) {
+                        return OkAt(false, at);
+                    }
+                }
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return OkAt(true, at),
+            }
+        }
+    }
+
+    /// Returns whether any remaining item matches `predicate`, stopping as soon as one does.
+    pub fn any(
+        &mut self,
+        mut predicate: impl FnMut(S::Output) -> bool,
+    ) -> ResultAt<bool, S::Error> {
+        loop {
+            match self.next() {
+                OkAt(x, at) => {
+                    if predicate(x) {
+                        return OkAt(true, at);
+                    }
+                }
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return OkAt(false, at),
+            }
+        }
+    }
+
+    /// Returns the `n`th remaining item (zero-indexed), discarding the ones before it.
+    pub fn nth(&mut self, mut n: usize) -> ResultAt<Option<S::Output>, S::Error> {
+        loop {
+            match self.next() {
+                OkAt(x, at) => {
+                    if n == 0 {
+                        return OkAt(Some(x), at);
+                    }
+
+                    n -= 1;
+                }
+                ErrAt(e, at) => return ErrAt(e, at),
+                NoneAt(at) => return OkAt(None, at),
+            }
+        }
+    }
+
+    /// Returns the last remaining item, if any.
+    pub fn last(&mut self) -> ResultAt<Option<S::Output>, S::Error> {
+        self.fold(None, |_, x| Some(x))
+    }
+
+    /// Collects every remaining item into a [`Vec`], stopping at the first `ErrAt`.
+    pub fn collect(&mut self) -> Result<Vec<S::Output>, S::Error> {
+        match self.fold(Vec::new(), |mut items, x| {
+            items.push(x);
+            items
+        }) {
+            OkAt(items, _) => Ok(items),
+            ErrAt(e, _) => Err(e),
+            NoneAt(_) => unreachable!(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = char>> Reader<CharSource<I>> {
+    /// The number of bytes consumed so far, not counting a character that has already been
+    /// peeked but not yet returned via [`Reader::next`]. Lets a caller that knows it's reading
+    /// from a `&str`-backed source slice directly into the original buffer instead of rebuilding
+    /// it character by character.
+    pub fn byte_pos(&self) -> usize {
+        let peeked_len: usize = self
+            .peeked
+            .iter()
+            .map(|r| match r {
+                OkAt(c, _) => c.len_utf8(),
+                _ => 0,
+            })
+            .sum();
+
+        self.reader.byte_pos() - peeked_len
+    }
+
+    /// The line/column the reader is currently positioned at: the start of whatever character
+    /// comes next, or, at end of input, the position that character would have begun at. Lets a
+    /// caller that has just finished consuming a multi-character construct (an identifier, a
+    /// string) report where it ends without having threaded a running position through the loop
+    /// that consumed it.
+    pub fn position(&mut self) -> Position {
+        match self.peek() {
+            OkAt(_, at) => at.start,
+            ErrAt(_, at) => at.start,
+            NoneAt(at) => at.start,
+        }
+    }
 }
 
-/// A [`Result`] with line/column position information.
+/// A [`Result`] with source span information.
 ///
-/// Supports `?`, returning `(T, Position)` on success.
+/// Supports `?`, returning `(T, Span)` on success.
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ResultAt<T, E> {
-    OkAt(T, Position),
-    ErrAt(E, Position),
-    NoneAt(Position),
+    OkAt(T, Span),
+    ErrAt(E, Span),
+    NoneAt(Span),
 }
 
 pub use ResultAt::*;
@@ -246,20 +823,20 @@ impl<T, E> ResultAt<T, E> {
     /// ```
     /// # use result_at::*;
     /// assert_eq!(
-    ///     OkAt::<_, &str>(42, (1, 1)).and_then(|x| Ok(x * 2)),
-    ///     OkAt::<_, &str>(84, (1, 1))
+    ///     OkAt::<_, &str>(42, Span::point((1, 1))).and_then(|x| Ok(x * 2)),
+    ///     OkAt::<_, &str>(84, Span::point((1, 1)))
     /// );
     /// assert_eq!(
-    ///     OkAt::<_, &str>(42, (1, 1)).and_then(|_| -> Result<usize, &str> {
+    ///     OkAt::<_, &str>(42, Span::point((1, 1))).and_then(|_| -> Result<usize, &str> {
     ///         Err("oh no")
     ///     }),
-    ///     ErrAt::<_, &str>("oh no", (1, 1))
+    ///     ErrAt::<_, &str>("oh no", Span::point((1, 1)))
     /// );
     /// assert_eq!(
-    ///     NoneAt::<_, &str>((1, 1)).and_then(|x: usize| -> Result<usize, &str> {
+    ///     NoneAt::<_, &str>(Span::point((1, 1))).and_then(|x: usize| -> Result<usize, &str> {
     ///         Ok(x * 2)
     ///     }),
-    ///     NoneAt::<_, &str>((1, 1))
+    ///     NoneAt::<_, &str>(Span::point((1, 1)))
     /// );
     /// ```
     pub fn and_then<O, E2: From<E>>(self, op: impl FnOnce(T) -> Result<O, E2>) -> ResultAt<O, E2> {
@@ -281,25 +858,25 @@ impl<T, E> ResultAt<T, E> {
     /// ```
     /// # use result_at::*;
     /// assert_eq!(
-    ///     OkAt::<_, &str>(42, (1, 1)).and_then_at(|x, _| OkAt(x * 2, (4, 4))),
-    ///     OkAt::<_, &str>(84, (4, 4))
+    ///     OkAt::<_, &str>(42, Span::point((1, 1))).and_then_at(|x, _| OkAt(x * 2, Span::point((4, 4)))),
+    ///     OkAt::<_, &str>(84, Span::point((4, 4)))
     /// );
     /// assert_eq!(
-    ///     OkAt::<_, &str>(42, (1, 1)).and_then_at(|_, at| -> ResultAt<usize, &str> {
+    ///     OkAt::<_, &str>(42, Span::point((1, 1))).and_then_at(|_, at| -> ResultAt<usize, &str> {
     ///         ErrAt("oh no", at)
     ///     }),
-    ///     ErrAt::<_, &str>("oh no", (1, 1))
+    ///     ErrAt::<_, &str>("oh no", Span::point((1, 1)))
     /// );
     /// assert_eq!(
-    ///     NoneAt::<_, &str>((1, 1)).and_then_at(|_: usize, _| -> ResultAt<usize, &str> {
+    ///     NoneAt::<_, &str>(Span::point((1, 1))).and_then_at(|_: usize, _| -> ResultAt<usize, &str> {
     ///         unreachable!();
     ///     }),
-    ///     NoneAt::<_, &str>((1, 1))
+    ///     NoneAt::<_, &str>(Span::point((1, 1)))
     /// );
     /// ```
     pub fn and_then_at<O, E2: From<E>>(
         self,
-        op: impl FnOnce(T, Position) -> ResultAt<O, E2>,
+        op: impl FnOnce(T, Span) -> ResultAt<O, E2>,
     ) -> ResultAt<O, E2> {
         match self {
             OkAt(x, at) => op(x, at),
@@ -335,8 +912,8 @@ impl<T, E> ResultAt<T, E> {
         }
     }
 
-    /// Returns the contained `OkAt` value and position or panics.
-    pub fn unwrap(self) -> (T, Position)
+    /// Returns the contained `OkAt` value and span or panics.
+    pub fn unwrap(self) -> (T, Span)
     where
         E: std::fmt::Debug,
     {
@@ -351,7 +928,7 @@ impl<T, E> ResultAt<T, E> {
     }
 
     /// Returns the contained `OkAt` value or computes it from a closure.
-    pub fn unwrap_or_else(self, op: impl FnOnce() -> T) -> (T, Position) {
+    pub fn unwrap_or_else(self, op: impl FnOnce() -> T) -> (T, Span) {
         match self {
             OkAt(x, at) => (x, at),
             ErrAt(_, at) => (op(), at),
@@ -368,8 +945,8 @@ impl<T, E> ResultAt<T, E> {
         }
     }
 
-    /// Returns an ok/erroring `ResultAt` from the given `Result` with the given position.
-    pub fn from_result<E2: Into<E>>(result: Result<T, E2>, at: Position) -> Self {
+    /// Returns an ok/erroring `ResultAt` from the given `Result` with the given span.
+    pub fn from_result<E2: Into<E>>(result: Result<T, E2>, at: Span) -> Self {
         match result {
             Ok(x) => OkAt(x, at),
             Err(e) => ErrAt(e.into(), at),
@@ -416,7 +993,7 @@ impl<T, E: From<E2>, E2> std::ops::FromResidual<ResultAt<std::convert::Infallibl
 }
 
 impl<T, E> std::ops::Try for ResultAt<T, E> {
-    type Output = (T, Position);
+    type Output = (T, Span);
     type Residual = ResultAt<std::convert::Infallible, E>;
 
     fn from_output(output: Self::Output) -> Self {
@@ -504,11 +1081,11 @@ mod tests {
             self.next += 1;
 
             if self.next == self.fails_at {
-                ErrAt(TestError {}, (0, self.next))
+                ErrAt(TestError {}, Span::point((0, self.next)))
             } else if self.next == self.ends_at {
-                NoneAt((0, self.next))
+                NoneAt(Span::point((0, self.next)))
             } else {
-                OkAt(self.next, (0, self.next))
+                OkAt(self.next, Span::point((0, self.next)))
             }
         }
     }
@@ -517,9 +1094,35 @@ mod tests {
     fn input_next_gives_peeked() {
         let mut input = test2_source().reader();
 
-        assert_eq!(input.next(), OkAt(1, (0, 1)));
-        assert_eq!(input.peek(), &OkAt(2, (0, 2)));
-        assert_eq!(input.next(), OkAt(2, (0, 2)));
+        assert_eq!(input.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(input.peek(), &OkAt(2, Span::point((0, 2))));
+        assert_eq!(input.next(), OkAt(2, Span::point((0, 2))));
+    }
+
+    #[test]
+    fn peek_nth_looks_ahead_without_consuming() {
+        let mut input = test2_source().reader();
+
+        assert_eq!(input.peek_nth(2), &OkAt(3, Span::point((0, 3))));
+        assert_eq!(input.peek_nth(0), &OkAt(1, Span::point((0, 1))));
+        assert_eq!(input.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(input.next(), OkAt(2, Span::point((0, 2))));
+        assert_eq!(input.next(), OkAt(3, Span::point((0, 3))));
+    }
+
+    #[test]
+    fn peek_nth_past_a_terminal_keeps_returning_it() {
+        let mut input = test2_source_ends_at(2).reader();
+
+        assert_eq!(input.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(input.peek_nth(0), &NoneAt(Span::point((0, 2))));
+        assert_eq!(input.peek_nth(3), &NoneAt(Span::point((0, 2))));
+        assert_eq!(input.next(), NoneAt(Span::point((0, 2))));
+
+        let mut input = test2_source_fails_at(2).reader();
+
+        assert_eq!(input.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(input.peek_nth(2), &ErrAt(TestError {}, Span::point((0, 2))));
     }
 
     #[test]
@@ -527,20 +1130,20 @@ mod tests {
         assert_eq!(
             test2_source_fails_at(4).reader().iter().collect::<Vec<_>>(),
             vec![
-                OkAt(1, (0, 1)),
-                OkAt(2, (0, 2)),
-                OkAt(3, (0, 3)),
-                ErrAt(TestError {}, (0, 4)),
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                OkAt(3, Span::point((0, 3))),
+                ErrAt(TestError {}, Span::point((0, 4))),
             ]
         );
 
         assert_eq!(
             test2_source_ends_at(4).reader().iter().collect::<Vec<_>>(),
             vec![
-                OkAt(1, (0, 1)),
-                OkAt(2, (0, 2)),
-                OkAt(3, (0, 3)),
-                NoneAt((0, 4))
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                OkAt(3, Span::point((0, 3))),
+                NoneAt(Span::point((0, 4)))
             ]
         );
     }
@@ -591,14 +1194,14 @@ mod tests {
         let mut input = test2_source_fails_at(4).reader();
         input.items_while_successful_if(|x| *x < 3).for_each(drop);
 
-        assert_eq!(input.next(), OkAt(3, (0, 3)));
+        assert_eq!(input.next(), OkAt(3, Span::point((0, 3))));
     }
 
     #[test]
     fn try_gives_contents_on_success() {
         assert_eq!(
-            (|| { OkAt::<_, TestError>(test2_source().reader().next()?, (0, 1)) })(),
-            OkAt((1, (0, 1)), (0, 1))
+            (|| { OkAt::<_, TestError>(test2_source().reader().next()?, Span::point((0, 1))) })(),
+            OkAt((1, Span::point((0, 1))), Span::point((0, 1)))
         );
     }
 
@@ -610,7 +1213,7 @@ mod tests {
 
                 panic!();
             })(),
-            ErrAt(TestError {}, (0, 1))
+            ErrAt(TestError {}, Span::point((0, 1)))
         );
 
         assert_eq!(
@@ -619,7 +1222,7 @@ mod tests {
 
                 panic!();
             })(),
-            NoneAt((0, 1))
+            NoneAt(Span::point((0, 1)))
         );
     }
 
@@ -627,11 +1230,376 @@ mod tests {
     fn try_can_convert_error_on_failure() {
         assert_eq!(
             (|| -> ResultAt<(), TestError> {
-                ErrAt::<(), _>(TestError2 {}, (1, 0))?;
+                ErrAt::<(), _>(TestError2 {}, Span::point((1, 0)))?;
 
                 panic!();
             })(),
-            ErrAt(TestError {}, (1, 0))
+            ErrAt(TestError {}, Span::point((1, 0)))
+        );
+    }
+
+    #[test]
+    fn map_transforms_oks_and_leaves_errs_and_nones_alone() {
+        assert_eq!(
+            test2_source_fails_at(3)
+                .map(|x| x * 10)
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(10, Span::point((0, 1))),
+                OkAt(20, Span::point((0, 2))),
+                ErrAt(TestError {}, Span::point((0, 3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn map_err_transforms_errs_and_leaves_oks_and_nones_alone() {
+        assert_eq!(
+            test2_source_fails_at(3)
+                .map_err(|_| "oh no")
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                ErrAt("oh no", Span::point((0, 3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_skips_non_matching_oks() {
+        assert_eq!(
+            test2_source_ends_at(5)
+                .filter(|x| x % 2 == 0)
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(2, Span::point((0, 2))),
+                OkAt(4, Span::point((0, 4))),
+                NoneAt(Span::point((0, 5))),
+            ]
+        );
+    }
+
+    #[test]
+    fn filter_map_transforms_and_skips_in_one_pass() {
+        assert_eq!(
+            test2_source_ends_at(5)
+                .filter_map(|x| if x % 2 == 0 { Some(x * 10) } else { None })
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(20, Span::point((0, 2))),
+                OkAt(40, Span::point((0, 4))),
+                NoneAt(Span::point((0, 5))),
+            ]
+        );
+    }
+
+    #[test]
+    fn and_then_can_fail_an_ok() {
+        assert_eq!(
+            test2_source_ends_at(3)
+                .and_then(|x| if x == 2 {
+                    Err(TestError {})
+                } else {
+                    Ok(x * 10)
+                })
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(10, Span::point((0, 1))),
+                ErrAt(TestError {}, Span::point((0, 2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn take_stops_after_n_items() {
+        assert_eq!(
+            test2_source().take(2).reader().iter().collect::<Vec<_>>(),
+            vec![
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                NoneAt(Span::point((0, 2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn take_still_reports_upstream_errs_and_nones() {
+        assert_eq!(
+            test2_source_fails_at(2)
+                .take(5)
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(1, Span::point((0, 1))),
+                ErrAt(TestError {}, Span::point((0, 2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_discards_n_items_then_continues() {
+        assert_eq!(
+            test2_source_ends_at(5)
+                .skip(2)
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(3, Span::point((0, 3))),
+                OkAt(4, Span::point((0, 4))),
+                NoneAt(Span::point((0, 5))),
+            ]
+        );
+    }
+
+    #[test]
+    fn skip_propagates_an_err_hit_during_the_skip() {
+        assert_eq!(
+            test2_source_fails_at(2)
+                .skip(5)
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![ErrAt(TestError {}, Span::point((0, 2)))]
+        );
+    }
+
+    #[test]
+    fn count_counts_all_items_on_clean_end() {
+        assert_eq!(
+            test2_source_ends_at(4).reader().count(),
+            OkAt(3, Span::point((0, 4)))
+        );
+    }
+
+    #[test]
+    fn count_stops_at_first_err() {
+        assert_eq!(
+            test2_source_fails_at(2).reader().count(),
+            ErrAt(TestError {}, Span::point((0, 2)))
+        );
+    }
+
+    #[test]
+    fn fold_accumulates_and_stops_at_first_err() {
+        assert_eq!(
+            test2_source_fails_at(3).reader().fold(0, |acc, x| acc + x),
+            ErrAt(TestError {}, Span::point((0, 3)))
+        );
+
+        assert_eq!(
+            test2_source_ends_at(4).reader().fold(0, |acc, x| acc + x),
+            OkAt(6, Span::point((0, 4)))
+        );
+    }
+
+    #[test]
+    fn try_fold_can_fail_early() {
+        assert_eq!(
+            test2_source_ends_at(4).reader().try_fold(0, |acc, x| {
+                if x == 2 {
+                    Err(TestError {})
+                } else {
+                    Ok(acc + x)
+                }
+            }),
+            ErrAt(TestError {}, Span::point((0, 2)))
+        );
+    }
+
+    #[test]
+    fn all_stops_at_first_non_match() {
+        assert_eq!(
+            test2_source_ends_at(10).reader().all(|x| x < 3),
+            OkAt(false, Span::point((0, 3)))
+        );
+
+        assert_eq!(
+            test2_source_ends_at(4).reader().all(|x| x < 10),
+            OkAt(true, Span::point((0, 4)))
+        );
+    }
+
+    #[test]
+    fn any_stops_at_first_match() {
+        assert_eq!(
+            test2_source_ends_at(10).reader().any(|x| x == 3),
+            OkAt(true, Span::point((0, 3)))
+        );
+
+        assert_eq!(
+            test2_source_ends_at(4).reader().any(|x| x > 10),
+            OkAt(false, Span::point((0, 4)))
+        );
+    }
+
+    #[test]
+    fn nth_returns_the_requested_item_or_none_past_the_end() {
+        assert_eq!(
+            test2_source_ends_at(10).reader().nth(2),
+            OkAt(Some(3), Span::point((0, 3)))
+        );
+
+        assert_eq!(
+            test2_source_ends_at(2).reader().nth(5),
+            OkAt(None, Span::point((0, 2)))
+        );
+    }
+
+    #[test]
+    fn last_returns_the_final_item() {
+        assert_eq!(
+            test2_source_ends_at(4).reader().last(),
+            OkAt(Some(3), Span::point((0, 4)))
+        );
+    }
+
+    #[test]
+    fn collect_gathers_items_or_stops_at_the_first_err() {
+        assert_eq!(
+            test2_source_ends_at(4).reader().collect(),
+            Ok(vec![1, 2, 3])
+        );
+
+        assert_eq!(
+            test2_source_fails_at(2).reader().collect(),
+            Err(TestError {})
+        );
+    }
+
+    #[test]
+    fn source_from_results_tracks_an_incrementing_position() {
+        let mut reader =
+            source_from_results(vec![Ok(1), Ok(2), Err(TestError {})].into_iter()).reader();
+
+        assert_eq!(reader.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(reader.next(), OkAt(2, Span::point((0, 2))));
+        assert_eq!(reader.next(), ErrAt(TestError {}, Span::point((0, 3))));
+    }
+
+    #[test]
+    fn source_from_results_reports_none_at_the_end() {
+        let mut reader = source_from_results::<usize, TestError>(vec![Ok(1)].into_iter()).reader();
+
+        assert_eq!(reader.next(), OkAt(1, Span::point((0, 1))));
+        assert_eq!(reader.next(), NoneAt(Span::point((0, 2))));
+    }
+
+    #[test]
+    fn results_from_source_drops_positions_and_stops_at_the_first_err() {
+        assert_eq!(
+            results_from_source(test2_source_fails_at(3)).collect::<Vec<_>>(),
+            vec![Ok(1), Ok(2), Err(TestError {})]
+        );
+    }
+
+    #[test]
+    fn results_from_source_stops_cleanly_at_the_end() {
+        assert_eq!(
+            results_from_source(test2_source_ends_at(3)).collect::<Vec<_>>(),
+            vec![Ok(1), Ok(2)]
+        );
+    }
+
+    #[test]
+    fn chain_switches_to_the_second_source_once_the_first_ends() {
+        assert_eq!(
+            test2_source_ends_at(3)
+                .chain(test2_source_ends_at(3))
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                OkAt(1, Span::point((0, 1))),
+                OkAt(2, Span::point((0, 2))),
+                NoneAt(Span::point((0, 3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn chain_passes_through_an_err_from_either_side() {
+        assert_eq!(
+            test2_source_fails_at(2)
+                .chain(test2_source_ends_at(3))
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt(1, Span::point((0, 1))),
+                ErrAt(TestError {}, Span::point((0, 2))),
+            ]
+        );
+    }
+
+    #[test]
+    fn zip_pairs_up_items_using_the_first_sources_position() {
+        assert_eq!(
+            test2_source_ends_at(3)
+                .zip(test2_source_ends_at(5))
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt((1, 1), Span::point((0, 1))),
+                OkAt((2, 2), Span::point((0, 2))),
+                NoneAt(Span::point((0, 3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn zip_at_combines_positions_with_the_given_closure() {
+        assert_eq!(
+            test2_source_ends_at(5)
+                .zip_at(test2_source_ends_at(5), |_first, second| Span::point((
+                    1,
+                    second.start.1
+                )))
+                .reader()
+                .next(),
+            OkAt((1, 1), Span::point((1, 1)))
+        );
+    }
+
+    #[test]
+    fn zip_surfaces_an_err_from_either_side() {
+        assert_eq!(
+            test2_source_fails_at(2)
+                .zip(test2_source_ends_at(5))
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt((1, 1), Span::point((0, 1))),
+                ErrAt(TestError {}, Span::point((0, 2))),
+            ]
+        );
+
+        assert_eq!(
+            test2_source_ends_at(5)
+                .zip(test2_source_fails_at(2))
+                .reader()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![
+                OkAt((1, 1), Span::point((0, 1))),
+                ErrAt(TestError {}, Span::point((0, 2))),
+            ]
         );
     }
 }
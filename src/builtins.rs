@@ -4,43 +4,112 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use alloc::rc::Rc;
+
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
 use lazy_static::lazy_static;
 
-use crate::types::{ArgumentSpec, Signature, Type, TypeSpec};
+use crate::types::{ArgumentSpec, Signature, Type, TypeSpec, Typeable};
 use crate::value::{self, Identifier, Value};
 use crate::vm::code;
 use crate::vm::Vm;
 
+/// A builtin's implementation, resolved once per call site and cached by `Vm` (see
+/// `Vm::call_internal`) so a hot loop calling the same builtin repeatedly only pays for the
+/// `BUILTINS` lookup on its first iteration.
+pub(crate) type BuiltinFn =
+    dyn Fn(&mut Vm<'_>, code::RegisterOffset) -> Result<(), code::Error> + Sync;
+
 pub(crate) struct Builtin {
     pub signature: Signature,
-    pub run: &'static (dyn Fn(&mut Vm<'_>, code::RegisterOffset) -> Result<(), code::Error> + Sync),
+    /// Whether `run` is free of observable side effects (besides its return value) and always
+    /// produces the same output for the same inputs, so a call to it can be evaluated ahead of
+    /// time by [`crate::vm::optimize`] when all of its arguments are compile-time constants.
+    pub pure: bool,
+    pub run: &'static BuiltinFn,
+}
+
+/// A number read out of a register for an arithmetic/comparison builtin, either `Integer` or
+/// `Float` — the numeric tower's two rungs. Combining two `Number`s (see [`Number::combine`])
+/// promotes to `Float` if either operand is a `Float`, and stays in `Integer` otherwise.
+#[derive(Clone, Copy, Debug)]
+enum Number {
+    Integer(isize),
+    Float(f64),
+}
+
+impl Number {
+    fn as_f64(self) -> f64 {
+        match self {
+            Number::Integer(i) => i as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    /// Combines `self` and `other` via `int_op` if both are `Integer`s, otherwise coerces both to
+    /// `f64` and combines them via `float_op`.
+    fn combine(
+        self,
+        other: Number,
+        int_op: fn(isize, isize) -> isize,
+        float_op: fn(f64, f64) -> f64,
+    ) -> Number {
+        match (self, other) {
+            (Number::Integer(a), Number::Integer(b)) => Number::Integer(int_op(a, b)),
+            (a, b) => Number::Float(float_op(a.as_f64(), b.as_f64())),
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Number::Integer(i) => Value::Integer(i),
+            Number::Float(f) => Value::Float(f.into()),
+        }
+    }
 }
 
-fn iter_as_integers(
+fn iter_as_numbers(
     registers: &code::Registers,
     num_args: code::RegisterOffset,
-) -> Result<impl Iterator<Item = isize>, value::Error> {
+) -> Result<impl Iterator<Item = Number>, code::Error> {
     Ok(registers
         .iter()
         .take(num_args as usize)
-        .map(|v| v.try_as_integer())
+        .enumerate()
+        .map(|(register, v)| match v {
+            Value::Integer(i) => Ok(Number::Integer(*i)),
+            Value::Float(f) => Ok(Number::Float(f.0)),
+            _ => Err(code::Error::TypeMismatch {
+                register: register as code::RegisterId,
+                expected: Type::Integer,
+                found: v.type_(),
+            }),
+        })
         .collect::<Result<Vec<_>, _>>()?
         .into_iter())
 }
 
 lazy_static! {
-    static ref BUILTINS: std::collections::HashMap<&'static str, Builtin> = {
-        let mut map = std::collections::HashMap::new();
+    static ref BUILTINS: HashMap<&'static str, Builtin> = {
+        let mut map = HashMap::new();
         map.insert(
             "+",
             Builtin {
                 signature: Signature::new()
-                    .add_rest(Type::Integer)
+                    .add_rest(TypeSpec::Numeric)
                     .return_type(Type::Integer)
                     .build(),
+                pure: true,
                 run: &|vm, num_args| {
-                    vm.registers[0] =
-                        Value::Integer(iter_as_integers(&vm.registers, num_args)?.sum());
+                    vm.registers[0] = iter_as_numbers(&vm.registers, num_args)?
+                        .fold(Number::Integer(0), |a, b| {
+                            a.combine(b, |x, y| x + y, |x, y| x + y)
+                        })
+                        .into_value();
 
                     Ok(())
                 },
@@ -50,15 +119,15 @@ lazy_static! {
             "-",
             Builtin {
                 signature: Signature::new()
-                    .add_rest(Type::Integer)
+                    .add_rest(TypeSpec::Numeric)
                     .return_type(Type::Integer)
                     .build(),
+                pure: true,
                 run: &|vm, num_args| {
-                    vm.registers[0] = Value::Integer(
-                        iter_as_integers(&vm.registers, num_args)?
-                            .reduce(|a, b| a - b)
-                            .unwrap_or(0),
-                    );
+                    vm.registers[0] = iter_as_numbers(&vm.registers, num_args)?
+                        .reduce(|a, b| a.combine(b, |x, y| x - y, |x, y| x - y))
+                        .unwrap_or(Number::Integer(0))
+                        .into_value();
 
                     Ok(())
                 },
@@ -68,14 +137,17 @@ lazy_static! {
             "<",
             Builtin {
                 signature: Signature::new()
-                    .add(Type::Integer)
-                    .add(Type::Integer)
+                    .add(TypeSpec::Numeric)
+                    .add(TypeSpec::Numeric)
                     .return_type(Type::Boolean)
                     .build(),
+                pure: true,
                 run: &|vm, _| {
-                    vm.registers[0] = Value::Boolean(
-                        vm.registers[0].try_as_integer()? < vm.registers[1].try_as_integer()?,
-                    );
+                    let mut numbers = iter_as_numbers(&vm.registers, 2)?;
+                    let lhs = numbers.next().unwrap();
+                    let rhs = numbers.next().unwrap();
+
+                    vm.registers[0] = Value::Boolean(lhs.as_f64() < rhs.as_f64());
 
                     Ok(())
                 },
@@ -88,6 +160,7 @@ lazy_static! {
                     .add_rest(TypeSpec::Any)
                     .return_type(Type::Nil)
                     .build(),
+                pure: false,
                 run: &|vm, num_args| {
                     let output: Vec<_> = vm
                         .registers
@@ -112,6 +185,7 @@ lazy_static! {
                     .add(ArgumentSpec::new(TypeSpec::List).raw(true))
                     .return_type(Type::Nil)
                     .build(),
+                pure: false,
                 run: &|_, _| unreachable!(),
             },
         );
@@ -123,9 +197,236 @@ lazy_static! {
                     .add(TypeSpec::Any)
                     .return_type(Type::Nil)
                     .build(),
+                pure: false,
+                run: &|_, _| unreachable!(),
+            },
+        );
+        map.insert(
+            "fn",
+            Builtin {
+                signature: Signature::new()
+                    .add(ArgumentSpec::new(Type::Identifier).raw(true))
+                    .add(ArgumentSpec::new(TypeSpec::List).raw(true))
+                    .add(ArgumentSpec::new(TypeSpec::List).raw(true))
+                    .return_type(Type::Nil)
+                    .build(),
+                pure: false,
+                run: &|_, _| unreachable!(),
+            },
+        );
+        map.insert(
+            "match",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::Any)
+                    .add_rest(ArgumentSpec::new(TypeSpec::Any).raw(true))
+                    .return_type(Type::Nil)
+                    .build(),
+                pure: false,
                 run: &|_, _| unreachable!(),
             },
         );
+        map.insert(
+            "__match",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::Any)
+                    .add(TypeSpec::Any)
+                    .return_type(Type::Boolean)
+                    .build(),
+                // Not `pure`, even though it has no side effects: on a match it writes captured
+                // bindings into registers past its own declared arguments (see below), which the
+                // constant folder isn't allowed to assume is limited to register 0 of the window.
+                pure: false,
+                // Called by `match`'s compiled code (see `compiler::CompilerVisitor::visit_match`)
+                // once per clause, with the subject in register 0 and that clause's pattern
+                // (loaded as a literal by the compiler) in register 1. On a match, the captured
+                // bindings are written directly into the registers right after this call's own
+                // arguments, in the same order `value::pattern_binding_names` walked the pattern
+                // in when the compiler reserved them.
+                run: &|vm, _| {
+                    let subject = vm.registers[0].clone();
+                    let pattern = vm.registers[1].clone();
+
+                    match subject.match_pattern(&pattern) {
+                        Some(bindings) => {
+                            for (i, binding) in bindings.into_iter().enumerate() {
+                                vm.registers[2 + i as code::RegisterId] = binding;
+                                vm.registers.mark_initialized(2 + i as code::RegisterId);
+                            }
+                            vm.registers[0] = Value::Boolean(true);
+                        }
+                        None => vm.registers[0] = Value::Boolean(false),
+                    }
+
+                    Ok(())
+                },
+            },
+        );
+        map.insert(
+            "quasiquote",
+            Builtin {
+                signature: Signature::new()
+                    .add(ArgumentSpec::new(TypeSpec::Any).raw(true))
+                    .return_type(Type::Cell)
+                    .build(),
+                pure: false,
+                run: &|_, _| unreachable!(),
+            },
+        );
+        map.insert(
+            "__cons",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::Any)
+                    .add(TypeSpec::Any)
+                    .return_type(Type::Cell)
+                    .build(),
+                pure: true,
+                // Called by `quasiquote`'s compiled code (see
+                // `compiler::CompilerVisitor::compile_quasiquote`) to build a `Cell` at runtime
+                // from an already-evaluated head and tail.
+                run: &|vm, _| {
+                    vm.registers[0] = Value::Cell(
+                        Rc::new(vm.registers[0].clone()),
+                        Rc::new(vm.registers[1].clone()),
+                    );
+
+                    Ok(())
+                },
+            },
+        );
+        map.insert(
+            "__append",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::List)
+                    .add(TypeSpec::Any)
+                    .return_type(Type::Cell)
+                    .build(),
+                pure: true,
+                // Called by `quasiquote`'s compiled code for an `unquote-splicing` element,
+                // concatenating the spliced list's elements onto `tail` (built from whatever
+                // follows the spliced element) rather than nesting the list as a single element.
+                run: &|vm, _| {
+                    let list = vm.registers[0].clone();
+                    let tail = vm.registers[1].clone();
+
+                    vm.registers[0] = list
+                        .iter_list()
+                        .collect::<value::Result<Vec<_>>>()?
+                        .into_iter()
+                        .rev()
+                        .fold(tail, |acc, elem| Value::Cell(Rc::new(elem.clone()), Rc::new(acc)));
+
+                    Ok(())
+                },
+            },
+        );
+
+        map.insert(
+            "cons",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::Any)
+                    .add(TypeSpec::Any)
+                    .return_type(Type::Cell)
+                    .build(),
+                pure: true,
+                run: &|vm, _| {
+                    vm.registers[0] = Value::Cell(
+                        Rc::new(vm.registers[0].clone()),
+                        Rc::new(vm.registers[1].clone()),
+                    );
+
+                    Ok(())
+                },
+            },
+        );
+        map.insert(
+            "car",
+            Builtin {
+                signature: Signature::new()
+                    .add(Type::Cell)
+                    .return_type(Type::Nil)
+                    .build(),
+                pure: true,
+                run: &|vm, _| {
+                    let (car, _) =
+                        vm.registers[0]
+                            .try_as_cell()
+                            .map_err(|_| code::Error::TypeMismatch {
+                                register: 0,
+                                expected: Type::Cell,
+                                found: vm.registers[0].type_(),
+                            })?;
+                    let car = car.clone();
+
+                    vm.registers[0] = car;
+
+                    Ok(())
+                },
+            },
+        );
+        map.insert(
+            "cdr",
+            Builtin {
+                signature: Signature::new()
+                    .add(Type::Cell)
+                    .return_type(Type::Nil)
+                    .build(),
+                pure: true,
+                run: &|vm, _| {
+                    let (_, cdr) =
+                        vm.registers[0]
+                            .try_as_cell()
+                            .map_err(|_| code::Error::TypeMismatch {
+                                register: 0,
+                                expected: Type::Cell,
+                                found: vm.registers[0].type_(),
+                            })?;
+                    let cdr = cdr.clone();
+
+                    vm.registers[0] = cdr;
+
+                    Ok(())
+                },
+            },
+        );
+        map.insert(
+            "nth",
+            Builtin {
+                signature: Signature::new()
+                    .add(TypeSpec::List)
+                    .add(Type::Integer)
+                    .return_type(Type::Nil)
+                    .build(),
+                pure: true,
+                run: &|vm, _| {
+                    let list = vm.registers[0].clone();
+                    let index = vm.registers[1]
+                        .try_as_integer()
+                        .map_err(|_| code::Error::TypeMismatch {
+                            register: 1,
+                            expected: Type::Integer,
+                            found: vm.registers[1].type_(),
+                        })?;
+
+                    let items = list.iter_list().collect::<value::Result<Vec<_>>>()?;
+                    let item = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| items.get(index).copied())
+                        .ok_or(code::Error::IndexOutOfBounds {
+                            index,
+                            length: items.len(),
+                        })?;
+
+                    vm.registers[0] = item.clone();
+
+                    Ok(())
+                },
+            },
+        );
 
         map
     };
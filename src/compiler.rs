@@ -4,11 +4,17 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::ops::Range;
-use std::rc::Rc;
 
-use result_at::Position;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+
+use result_at::Span;
 use thiserror::Error;
 
 use crate::builtins;
@@ -17,6 +23,7 @@ use crate::types::{self, Typeable};
 use crate::value;
 use crate::value::Value;
 use crate::vm::code::{self, Instruction};
+use crate::vm::ArgumentPositions;
 
 #[derive(Error, Debug, Eq, PartialEq)]
 pub enum ErrorInternal {
@@ -32,6 +39,15 @@ pub enum ErrorInternal {
     },
     #[error("unknown internal function: {0}")]
     UnknownInternalFunction(value::Identifier),
+    #[error("functions can only be defined at the top level of a program")]
+    FunctionDefinitionNotAtTopLevel,
+    #[error("can't resolve recursive call: {source}")]
+    UnresolvedLabel {
+        #[from]
+        source: code::InstructionError,
+    },
+    #[error("match clauses must be given as pattern/body pairs")]
+    MatchClausesNotInPairs,
     #[error("placeholder")]
     Placeholder,
 }
@@ -43,10 +59,10 @@ pub struct Error {
     column: usize,
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
         write!(
             f,
             "{} (at line {}, column {})",
@@ -55,13 +71,17 @@ impl std::fmt::Display for Error {
     }
 }
 
-type Result<T> = std::result::Result<T, Error>;
-type IResult<T> = std::result::Result<T, ErrorInternal>;
+type Result<T> = core::result::Result<T, Error>;
+type IResult<T> = core::result::Result<T, ErrorInternal>;
 
 struct RegisterAllocator {
     highest_used: code::RegisterId,
     current: RegisterAllocation,
     stack: Vec<RegisterAllocation>,
+    /// Registers below `current.end` whose values are dead and can be handed back out by
+    /// `alloc()`, so `highest_used` reflects peak simultaneous liveness instead of the total
+    /// number of registers ever touched.
+    free: Vec<code::RegisterId>,
 }
 
 type RegisterAllocation = Range<code::RegisterId>;
@@ -72,12 +92,13 @@ impl RegisterAllocator {
             highest_used: 0,
             current: 0..0,
             stack: Vec::new(),
+            free: Vec::new(),
         }
     }
 
     fn push_range(&mut self) {
         let start = self.current.end;
-        self.stack.push(std::mem::take(&mut self.current));
+        self.stack.push(core::mem::take(&mut self.current));
         self.current = start..start;
     }
 
@@ -94,9 +115,14 @@ impl RegisterAllocator {
         self.current.end
     }
 
+    /// Returns a register for a new value, reusing a freed one at the current allocation
+    /// frontier if one's available rather than growing `highest_used` further.
     fn alloc(&mut self) -> code::RegisterId {
-        let register_id = self.current.end;
-        self.current.end += 1;
+        let register_id = match self.free.iter().position(|&id| id == self.current.end) {
+            Some(pos) => self.free.swap_remove(pos),
+            None => self.current.end,
+        };
+        self.current.end = register_id + 1;
 
         if register_id > self.highest_used {
             self.highest_used = register_id;
@@ -104,6 +130,12 @@ impl RegisterAllocator {
 
         register_id
     }
+
+    /// Marks `id`'s value as dead, making it available to a future `alloc()` once the
+    /// allocation frontier reaches it again.
+    fn free(&mut self, id: code::RegisterId) {
+        self.free.push(id);
+    }
 }
 
 trait Visitor<'p> {
@@ -111,11 +143,11 @@ trait Visitor<'p> {
 
     fn label_with_value<T, E: Into<ErrorInternal>>(
         &self,
-        r: std::result::Result<T, E>,
+        r: core::result::Result<T, E>,
         value: &Rc<Value>,
     ) -> Result<T> {
         r.map_err(|e| {
-            let (line, column) = self.get_positions()[value];
+            let (line, column) = self.get_positions()[value].start;
             Error {
                 error: e.into(),
                 line,
@@ -126,9 +158,11 @@ trait Visitor<'p> {
 
     fn label_with_position<T, E: Into<ErrorInternal>>(
         &self,
-        r: std::result::Result<T, E>,
-        (line, column): Position,
+        r: core::result::Result<T, E>,
+        at: Span,
     ) -> Result<T> {
+        let (line, column) = at.start;
+
         r.map_err(|e| Error {
             error: e.into(),
             line,
@@ -163,15 +197,22 @@ trait Visitor<'p> {
 
     fn visit_program(&mut self, program: Rc<Value>) -> Result<()> {
         for maybe_item in Value::iter_list_rc(program) {
-            self.visit_statement(self.label_with_position(maybe_item, (1, 1))?)?;
+            self.visit_statement(self.label_with_position(maybe_item, Span::point((1, 1)))?)?;
         }
 
         Ok(())
     }
 }
 
+#[derive(Clone, Copy)]
+struct FunctionSignature {
+    num_params: usize,
+    return_type: types::Type,
+}
+
 struct TypeCheckVisitor<'p> {
     variables: HashMap<String, types::Type>,
+    functions: HashMap<String, FunctionSignature>,
     positions: &'p parser::PositionMap,
 }
 
@@ -191,6 +232,7 @@ impl<'p> TypeCheckVisitor<'p> {
     fn new(positions: &'p parser::PositionMap) -> Self {
         Self {
             variables: HashMap::new(),
+            functions: HashMap::new(),
             positions,
         }
     }
@@ -204,9 +246,116 @@ impl<'p> TypeCheckVisitor<'p> {
         Ok(value_type)
     }
 
+    /// Defines `fn name (params) { body }`, type-checking the body with each parameter assumed to
+    /// be an integer (there's no argument-type annotation syntax yet) and recording the function's
+    /// arity and inferred return type for its call sites. A recursive call to `name` from within
+    /// its own body is type-checked against a provisional signature that, like each parameter,
+    /// assumes an integer return type; this is only ever wrong for a function whose non-recursive
+    /// return path produces something else, which the final signature inserted into `self.functions`
+    /// will then correctly reflect for everyone *else's* call sites.
+    fn visit_fn(&mut self, args: Vec<Rc<Value>>) -> Result<types::Type> {
+        let name = args[0].try_as_identifier().unwrap().to_string();
+        let params = self.label_with_value(
+            Value::iter_list_rc(args[1].clone()).collect::<value::Result<Vec<_>>>(),
+            &args[1],
+        )?;
+
+        let mut body_visitor = TypeCheckVisitor {
+            variables: params
+                .iter()
+                .map(|p| {
+                    (
+                        p.try_as_identifier().unwrap().to_string(),
+                        types::Type::Integer,
+                    )
+                })
+                .collect(),
+            functions: self.functions.clone(),
+            positions: self.positions,
+        };
+        body_visitor.functions.insert(
+            name.clone(),
+            FunctionSignature {
+                num_params: params.len(),
+                return_type: types::Type::Integer,
+            },
+        );
+
+        let return_type = body_visitor.visit_block(args[2].clone())?;
+
+        self.functions.insert(
+            name,
+            FunctionSignature {
+                num_params: params.len(),
+                return_type,
+            },
+        );
+
+        Ok(types::Type::Nil)
+    }
+
+    /// Type-checks `match subject pat1 body1 pat2 body2 ...`, same as `if`'s lax `"if" => Ok(Nil)`
+    /// arm: this only validates that the clauses are given in pattern/body pairs, not the clause
+    /// bodies themselves.
+    fn visit_match(&mut self, args: Vec<Rc<Value>>) -> Result<types::Type> {
+        if (args.len() - 1) % 2 != 0 {
+            return self.label_with_value(
+                Err(ErrorInternal::MatchClausesNotInPairs),
+                &args[args.len() - 1],
+            );
+        }
+
+        Ok(types::Type::Nil)
+    }
+
+    fn visit_block(&mut self, block: Rc<Value>) -> Result<types::Type> {
+        let mut last_type = types::Type::Nil;
+
+        for maybe_item in Value::iter_list_rc(block) {
+            last_type =
+                self.visit_expr(self.label_with_position(maybe_item, Span::point((1, 1)))?)?;
+        }
+
+        Ok(last_type)
+    }
+
+    fn visit_function_call(
+        &mut self,
+        signature: FunctionSignature,
+        r: &Rc<Value>,
+        args: Vec<Rc<Value>>,
+    ) -> Result<types::Type> {
+        self.label_with_value(
+            if args.len() < signature.num_params {
+                Err(types::Error::NotEnoughArguments {
+                    expected: signature.num_params,
+                    actual: args.len(),
+                })
+            } else if args.len() > signature.num_params {
+                Err(types::Error::TooManyArguments {
+                    expected: signature.num_params,
+                    actual: args.len(),
+                })
+            } else {
+                Ok(())
+            },
+            r,
+        )?;
+
+        for arg in args {
+            self.visit_expr(arg)?;
+        }
+
+        Ok(signature.return_type)
+    }
+
     fn visit_call(&mut self, l: Rc<Value>, r: Rc<Value>) -> Result<types::Type> {
         let (fn_name, args) = self.collect_call(&l, r.clone())?;
 
+        if let Some(&signature) = self.functions.get(fn_name.as_str()) {
+            return self.visit_function_call(signature, &r, args);
+        }
+
         let builtin = self.get_builtin(&l)?;
 
         self.label_with_value(builtin.signature.check_arguments_length(args.len()), &r)?;
@@ -228,15 +377,20 @@ impl<'p> TypeCheckVisitor<'p> {
         match fn_name.as_str() {
             "set" => self.visit_set(args),
             "if" => Ok(types::Type::Nil),
+            "fn" => self.visit_fn(args),
+            "match" => self.visit_match(args),
             _ => Ok(builtin.signature.return_type),
         }
     }
 
     fn visit_expr(&mut self, expr: Rc<Value>) -> Result<types::Type> {
         match &*expr {
-            Value::Integer(_) | Value::Boolean(_) | Value::String(_) | Value::Nil => {
-                Ok(expr.type_())
-            }
+            Value::Integer(_)
+            | Value::Boolean(_)
+            | Value::String(_)
+            | Value::Nil
+            | Value::Float(_)
+            | Value::Char(_) => Ok(expr.type_()),
             Value::Identifier(i) => Ok(self.variables[i]),
             Value::Cell(l, r) => self.visit_call(l.clone(), r.clone()),
             _ => todo!("can't visit value: {}", expr),
@@ -244,14 +398,35 @@ impl<'p> TypeCheckVisitor<'p> {
     }
 }
 
-struct CompilerVisitor<'o, 'a, 'p> {
+struct CompilerVisitor<'o, 'a, 'p, 'f> {
     output: &'o mut Vec<Instruction>,
     allocator: &'a mut RegisterAllocator,
     positions: &'p parser::PositionMap,
     variables: HashMap<String, code::RegisterId>,
+    /// Start `Pc` of every function defined so far, keyed by name. Populated only while compiling
+    /// the top-level program (see `is_top_level`); functions are placed inline where they're
+    /// defined, so a call site can only resolve a target that's already in this map.
+    function_start_pcs: &'f mut HashMap<String, code::Pc>,
+    /// Source span of each argument passed to a builtin by a `CallInternal` this visitor has
+    /// emitted so far, keyed by that instruction's `Pc` *relative to `output`*. Since `output` is
+    /// often a scratch buffer (an `if` branch, a function body) spliced into a parent visitor's
+    /// own `output` later, these keys get shifted by the splice point when absorbed into the
+    /// parent (see `absorb_argument_positions`); only once compilation reaches the top level are
+    /// they truly absolute `Pc`s into the finished program.
+    argument_positions: ArgumentPositions,
+    /// The name of the function whose body this visitor is currently compiling, if any, so a
+    /// call to it from within its own body (recursion) can be recognized even though its start
+    /// `Pc` isn't known yet — see `pending_calls`.
+    recursive_function_name: Option<String>,
+    /// `Pc`s (relative to `output`, same splice-shifting caveat as `argument_positions`) of
+    /// `CallFunction` placeholders emitted for a recursive call, paired with the callee's name
+    /// and call-site span so `compile` can patch in the real target — or report an unresolved
+    /// label — once the whole program, and thus every function's start `Pc`, has been compiled.
+    pending_calls: Vec<(code::Pc, String, Span)>,
+    is_top_level: bool,
 }
 
-impl<'p> Visitor<'p> for CompilerVisitor<'_, '_, 'p> {
+impl<'p> Visitor<'p> for CompilerVisitor<'_, '_, 'p, '_> {
     fn get_positions(&self) -> &'p parser::PositionMap {
         self.positions
     }
@@ -261,17 +436,24 @@ impl<'p> Visitor<'p> for CompilerVisitor<'_, '_, 'p> {
     }
 }
 
-impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
+impl<'o, 'a, 'p, 'f> CompilerVisitor<'o, 'a, 'p, 'f> {
     fn new(
         output: &'o mut Vec<Instruction>,
         allocator: &'a mut RegisterAllocator,
         positions: &'p parser::PositionMap,
+        function_start_pcs: &'f mut HashMap<String, code::Pc>,
+        is_top_level: bool,
     ) -> Self {
         Self {
             output,
             allocator,
             positions,
             variables: HashMap::new(),
+            function_start_pcs,
+            argument_positions: ArgumentPositions::new(),
+            recursive_function_name: None,
+            pending_calls: Vec::new(),
+            is_top_level,
         }
     }
 
@@ -279,10 +461,32 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
         self.output.push(i);
     }
 
-    fn extend(&mut self, i: impl std::iter::IntoIterator<Item = code::Instruction>) {
+    fn extend(&mut self, i: impl core::iter::IntoIterator<Item = code::Instruction>) {
         self.output.extend(i);
     }
 
+    /// Merges `ArgumentPositions` recorded by a scratch-buffer visitor (an `if` branch, a
+    /// function body) into this visitor's own, shifting every `Pc` by `base` — the length of
+    /// `self.output` at the point the scratch buffer was spliced in.
+    fn absorb_argument_positions(&mut self, base: code::Pc, positions: ArgumentPositions) {
+        self.argument_positions
+            .extend(positions.into_iter().map(|(pc, spans)| (pc + base, spans)));
+    }
+
+    /// Merges `pending_calls` recorded by a scratch-buffer visitor into this visitor's own,
+    /// shifting every `Pc` by `base` exactly like `absorb_argument_positions`.
+    fn absorb_pending_calls(
+        &mut self,
+        base: code::Pc,
+        pending_calls: Vec<(code::Pc, String, Span)>,
+    ) {
+        self.pending_calls.extend(
+            pending_calls
+                .into_iter()
+                .map(|(pc, name, span)| (pc + base, name, span)),
+        );
+    }
+
     fn visit_set(&mut self, args: Vec<Rc<Value>>) -> Result<()> {
         let name = args[0].try_as_identifier().unwrap();
 
@@ -302,21 +506,42 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
         self.visit_expr(args[0].clone())?;
 
         let mut true_output = Vec::new();
-        {
-            CompilerVisitor::new(&mut true_output, &mut self.allocator, &self.positions)
-                .visit_program(args[1].clone())?;
-        }
+        let (true_positions, true_pending_calls) = {
+            let mut visitor = CompilerVisitor::new(
+                &mut true_output,
+                &mut self.allocator,
+                &self.positions,
+                &mut self.function_start_pcs,
+                false,
+            );
+            visitor.recursive_function_name = self.recursive_function_name.clone();
+            visitor.visit_program(args[1].clone())?;
+
+            (visitor.argument_positions, visitor.pending_calls)
+        };
 
         let mut false_output = Vec::new();
-        {
-            CompilerVisitor::new(&mut false_output, &mut self.allocator, &self.positions)
-                .visit_program(args[2].clone())?;
-        }
+        let (false_positions, false_pending_calls) = {
+            let mut visitor = CompilerVisitor::new(
+                &mut false_output,
+                &mut self.allocator,
+                &self.positions,
+                &mut self.function_start_pcs,
+                false,
+            );
+            visitor.recursive_function_name = self.recursive_function_name.clone();
+            visitor.visit_program(args[2].clone())?;
+
+            (visitor.argument_positions, visitor.pending_calls)
+        };
 
         self.push(JumpIf {
             cond,
             distance: false_output.len() as code::PcOffset + 2,
         });
+        let false_base = self.output.len() as code::Pc;
+        self.absorb_argument_positions(false_base, false_positions);
+        self.absorb_pending_calls(false_base, false_pending_calls);
         self.extend(false_output);
         let always_cond = self.allocator.current();
         self.visit_expr(Rc::new(Value::Boolean(true)))?;
@@ -325,6 +550,9 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
             distance: true_output.len() as code::PcOffset,
         });
 
+        let true_base = self.output.len() as code::Pc;
+        self.absorb_argument_positions(true_base, true_positions);
+        self.absorb_pending_calls(true_base, true_pending_calls);
         self.extend(true_output);
 
         self.allocator.pop_range();
@@ -332,6 +560,251 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
         Ok(())
     }
 
+    /// Compiles `match subject pat1 body1 pat2 body2 ...`, trying each pattern against the
+    /// subject in turn via the `__match` builtin (see `value::Value::match_pattern`) and running
+    /// the first clause whose pattern matches, using the same "always-true `JumpIf`" skip trick
+    /// `visit_if` uses to leave every other clause's body un-executed. A clause's bindings are
+    /// visible only to its own body, the same way `visit_fn`'s parameters are scoped to its body
+    /// and nothing else.
+    fn visit_match(&mut self, args: Vec<Rc<Value>>) -> Result<()> {
+        self.allocator.push_range();
+
+        let subject_reg = self.allocator.current();
+        self.visit_expr(args[0].clone())?;
+
+        let (clauses_output, clauses_positions, clauses_pending_calls) =
+            self.compile_match_clauses(subject_reg, &args[1..])?;
+
+        let base = self.output.len() as code::Pc;
+        self.absorb_argument_positions(base, clauses_positions);
+        self.absorb_pending_calls(base, clauses_pending_calls);
+        self.extend(clauses_output);
+
+        self.allocator.pop_range();
+
+        Ok(())
+    }
+
+    /// Compiles `clauses` (alternating pattern/body values) into a standalone instruction
+    /// sequence, building it up back-to-front so each clause's "skip the rest of the chain"
+    /// `JumpIf` can be given the already-known length of everything after it — the same
+    /// bottom-up approach `visit_if` uses for its single true/false branch pair, generalized to
+    /// a chain of `N` clauses. An empty `clauses` compiles to nothing, i.e. a `match` with no
+    /// remaining clause simply falls through.
+    fn compile_match_clauses(
+        &mut self,
+        subject_reg: code::RegisterId,
+        clauses: &[Rc<Value>],
+    ) -> Result<(Vec<Instruction>, ArgumentPositions, Vec<(code::Pc, String, Span)>)> {
+        use code::Instruction::*;
+
+        if clauses.is_empty() {
+            return Ok((Vec::new(), ArgumentPositions::new(), Vec::new()));
+        }
+
+        let pattern = clauses[0].clone();
+        let body = clauses[1].clone();
+
+        let (tail_output, tail_positions, tail_pending_calls) =
+            self.compile_match_clauses(subject_reg, &clauses[2..])?;
+
+        let mut output = Vec::new();
+
+        let bindings = value::pattern_binding_names(&pattern);
+        let call_base = self.allocator.alloc();
+        let pattern_reg = self.allocator.alloc();
+        output.push(Copy {
+            dest: call_base,
+            src: subject_reg,
+        });
+        output.push(LoadImmediate {
+            dest: pattern_reg,
+            value: (*pattern).clone(),
+        });
+
+        let mut body_variables = HashMap::new();
+        for name in &bindings {
+            body_variables.insert(name.to_string(), self.allocator.alloc());
+        }
+
+        output.push(CallInternal {
+            ident: value::identifier("__match"),
+            base: call_base,
+            num_args: 2,
+        });
+
+        let mut body_output = Vec::new();
+        let (body_positions, body_pending_calls) = {
+            let mut visitor = CompilerVisitor::new(
+                &mut body_output,
+                &mut self.allocator,
+                &self.positions,
+                &mut self.function_start_pcs,
+                false,
+            );
+            visitor.recursive_function_name = self.recursive_function_name.clone();
+            visitor.variables = body_variables;
+            visitor.visit_program(body)?;
+
+            (visitor.argument_positions, visitor.pending_calls)
+        };
+
+        // If this clause's pattern matched, skip the rest of the chain and land on this
+        // clause's body.
+        output.push(JumpIf {
+            cond: call_base,
+            distance: tail_output.len() as code::PcOffset + 2,
+        });
+
+        let tail_base = output.len() as code::Pc;
+        let tail_positions: ArgumentPositions = tail_positions
+            .into_iter()
+            .map(|(pc, spans)| (pc + tail_base, spans))
+            .collect();
+        let tail_pending_calls: Vec<_> = tail_pending_calls
+            .into_iter()
+            .map(|(pc, name, span)| (pc + tail_base, name, span))
+            .collect();
+        output.extend(tail_output);
+
+        // Otherwise, the rest of the chain already ran (or fell all the way through); skip this
+        // clause's body unconditionally.
+        let always_cond = self.allocator.alloc();
+        output.push(LoadImmediate {
+            dest: always_cond,
+            value: Value::Boolean(true),
+        });
+        output.push(JumpIf {
+            cond: always_cond,
+            distance: body_output.len() as code::PcOffset,
+        });
+
+        let body_base = output.len() as code::Pc;
+        let body_positions: ArgumentPositions = body_positions
+            .into_iter()
+            .map(|(pc, spans)| (pc + body_base, spans))
+            .collect();
+        let body_pending_calls: Vec<_> = body_pending_calls
+            .into_iter()
+            .map(|(pc, name, span)| (pc + body_base, name, span))
+            .collect();
+        output.extend(body_output);
+
+        let mut positions = tail_positions;
+        positions.extend(body_positions);
+        let mut pending_calls = tail_pending_calls;
+        pending_calls.extend(body_pending_calls);
+
+        Ok((output, positions, pending_calls))
+    }
+
+    /// Compiles `quasiquote template`, reconstructing `template` at runtime with `unquote` and
+    /// `unquote-splicing` substitutions applied. The whole thing is straight-line code (no
+    /// branching like `visit_if`/`visit_match` need), but since its pieces are built bottom-up by
+    /// `compile_quasiquote` and only the final `CallInternal`'s result is guaranteed to land at
+    /// the pre-call register frontier every other expression leaves its value at, this copies the
+    /// result there explicitly when the two differ.
+    fn visit_quasiquote(&mut self, args: Vec<Rc<Value>>) -> Result<()> {
+        use code::Instruction::*;
+
+        let entry_reg = self.allocator.current();
+
+        self.allocator.push_range();
+        let result = self.compile_quasiquote(args[0].clone())?;
+        self.allocator.pop_range();
+
+        if result != entry_reg {
+            self.push(Copy {
+                dest: entry_reg,
+                src: result,
+            });
+        }
+        self.allocator.extend_to(entry_reg);
+
+        Ok(())
+    }
+
+    /// Compiles a single `quasiquote` template value, returning the register holding its result.
+    /// An ordinary non-list subform is reproduced as a literal `Value` via a single
+    /// `LoadImmediate`; a list is always rebuilt cell by cell with `__cons`, even when it contains
+    /// no `unquote` anywhere; `(unquote expr)`
+    /// evaluates `expr` and substitutes the result in place; and within a list, an element shaped
+    /// `(unquote-splicing expr)` evaluates `expr` (which must yield a list) and concatenates its
+    /// elements onto the recursively-compiled tail via `__append`, rebuilding the tail rather than
+    /// nesting it, so `(a ,@xs b)` flattens instead of becoming `(a (x y z) b)`.
+    fn compile_quasiquote(&mut self, template: Rc<Value>) -> Result<code::RegisterId> {
+        use code::Instruction::*;
+
+        if let Some(expr) = match_unquote_form(&template, "unquote") {
+            return self.visit_expr_into_register(expr);
+        }
+
+        match &*template {
+            Value::Cell(car, cdr) => {
+                let car = car.clone();
+                let cdr = cdr.clone();
+
+                if let Some(expr) = match_unquote_form(&car, "unquote-splicing") {
+                    let spliced = self.visit_expr_into_register(expr)?;
+                    let tail = self.compile_quasiquote(cdr)?;
+
+                    Ok(self.emit_binary_call("__append", spliced, tail))
+                } else {
+                    let elem = self.compile_quasiquote(car)?;
+                    let tail = self.compile_quasiquote(cdr)?;
+
+                    Ok(self.emit_binary_call("__cons", elem, tail))
+                }
+            }
+            _ => {
+                let reg = self.allocator.alloc();
+                self.push(LoadImmediate {
+                    dest: reg,
+                    value: (*template).clone(),
+                });
+
+                Ok(reg)
+            }
+        }
+    }
+
+    /// Compiles `expr` and returns the register its value ends up in — the allocation frontier
+    /// at the point `expr` starts compiling, by the same convention every expression's compiled
+    /// code relies on (see `visit_set`).
+    fn visit_expr_into_register(&mut self, expr: Rc<Value>) -> Result<code::RegisterId> {
+        let reg = self.allocator.current();
+        self.visit_expr(expr)?;
+
+        Ok(reg)
+    }
+
+    /// Emits a 2-argument `CallInternal` to an internal builtin used to stitch together
+    /// `quasiquote`'s runtime-constructed pieces, copying `a` and `b` into a fresh contiguous
+    /// register window first since `CallInternal` requires its arguments to be contiguous.
+    /// Returns the register holding the result (the window's first register, by the same
+    /// convention every `CallInternal` call site relies on).
+    fn emit_binary_call(
+        &mut self,
+        ident: &str,
+        a: code::RegisterId,
+        b: code::RegisterId,
+    ) -> code::RegisterId {
+        use code::Instruction::*;
+
+        let base = self.allocator.alloc();
+        let arg2 = self.allocator.alloc();
+
+        self.push(Copy { dest: base, src: a });
+        self.push(Copy { dest: arg2, src: b });
+        self.push(CallInternal {
+            ident: value::identifier(ident),
+            base,
+            num_args: 2,
+        });
+
+        base
+    }
+
     fn visit_call(&mut self, l: Rc<Value>, r: Rc<Value>) -> Result<()> {
         use code::Instruction::*;
 
@@ -340,12 +813,26 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
         match fn_name.as_str() {
             "if" => return self.visit_if(args),
             "set" => return self.visit_set(args),
+            "fn" => return self.visit_fn(args),
+            "match" => return self.visit_match(args),
+            "quasiquote" => return self.visit_quasiquote(args),
             _ => {}
         }
 
+        if let Some(&target) = self.function_start_pcs.get(fn_name.as_str()) {
+            return self.visit_function_call(target, args);
+        }
+
+        if self.recursive_function_name.as_deref() == Some(fn_name.as_str()) {
+            let span = self.positions[&l].clone();
+
+            return self.visit_recursive_function_call(fn_name.clone(), span, args);
+        }
+
         self.get_builtin(&l)?;
 
         let num_args = args.len() as code::RegisterOffset;
+        let arg_spans: Vec<Span> = args.iter().map(|arg| self.positions[arg].clone()).collect();
 
         self.allocator.push_range();
         let base = self.allocator.current();
@@ -354,11 +841,87 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
             self.visit_expr(arg)?;
         }
 
+        let pc = self.output.len() as code::Pc;
         self.push(CallInternal {
             ident: fn_name.clone(),
             base,
             num_args,
         });
+        self.argument_positions.insert(pc, arg_spans);
+
+        // `base` itself goes on to hold the call's result; only the registers after it held
+        // arguments that are now fully consumed.
+        for extra in (base + 1)..(base + num_args as code::RegisterId) {
+            self.allocator.free(extra);
+        }
+
+        self.allocator.pop_range();
+        self.allocator.extend_to(base);
+
+        Ok(())
+    }
+
+    fn visit_function_call(&mut self, target: code::Pc, args: Vec<Rc<Value>>) -> Result<()> {
+        use code::Instruction::*;
+
+        let num_args = args.len() as code::RegisterOffset;
+
+        self.allocator.push_range();
+        let base = self.allocator.current();
+
+        for arg in args.into_iter() {
+            self.visit_expr(arg)?;
+        }
+
+        self.push(CallFunction {
+            target,
+            base,
+            num_args,
+        });
+
+        for extra in (base + 1)..(base + num_args as code::RegisterId) {
+            self.allocator.free(extra);
+        }
+
+        self.allocator.pop_range();
+        self.allocator.extend_to(base);
+
+        Ok(())
+    }
+
+    /// Compiles a call to the function whose body this visitor is currently compiling, i.e. a
+    /// recursive call. Its start `Pc` isn't known yet (that's only discovered once the whole
+    /// body has finished compiling, back in `visit_fn`), so this emits a placeholder
+    /// `CallFunction` and records `(pc, name, span)` in `pending_calls` for `compile` to patch
+    /// once every function's start `Pc` is known.
+    fn visit_recursive_function_call(
+        &mut self,
+        name: String,
+        span: Span,
+        args: Vec<Rc<Value>>,
+    ) -> Result<()> {
+        use code::Instruction::*;
+
+        let num_args = args.len() as code::RegisterOffset;
+
+        self.allocator.push_range();
+        let base = self.allocator.current();
+
+        for arg in args.into_iter() {
+            self.visit_expr(arg)?;
+        }
+
+        let pc = self.output.len() as code::Pc;
+        self.push(CallFunction {
+            target: code::Pc::MAX,
+            base,
+            num_args,
+        });
+        self.pending_calls.push((pc, name, span));
+
+        for extra in (base + 1)..(base + num_args as code::RegisterId) {
+            self.allocator.free(extra);
+        }
 
         self.allocator.pop_range();
         self.allocator.extend_to(base);
@@ -366,11 +929,118 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
         Ok(())
     }
 
+    /// Compiles `fn name (params) { body }` into its own instruction range, placed inline right
+    /// here with an unconditional jump over it (the same "always-true `JumpIf`" trick `visit_if`
+    /// uses to skip a branch) so normal top-down execution doesn't fall into it. Each parameter is
+    /// bound to a register in a fresh, isolated `RegisterAllocator` starting at 0; at the call
+    /// site, `CallFunction` relocates that numbering onto the caller's registers the same way
+    /// `CallInternal` does for builtins, via `Registers::push_window_starting`.
+    fn visit_fn(&mut self, args: Vec<Rc<Value>>) -> Result<()> {
+        use code::Instruction::*;
+
+        if !self.is_top_level {
+            return self.label_with_value(
+                Err(ErrorInternal::FunctionDefinitionNotAtTopLevel),
+                &args[0],
+            );
+        }
+
+        let name = args[0].try_as_identifier().unwrap().to_string();
+        let param_names: Vec<String> = Value::iter_list_rc(args[1].clone())
+            .collect::<value::Result<Vec<_>>>()
+            .unwrap()
+            .iter()
+            .map(|p| p.try_as_identifier().unwrap().to_string())
+            .collect();
+
+        let mut body_allocator = RegisterAllocator::new();
+        let mut body_variables = HashMap::new();
+        for param_name in &param_names {
+            body_variables.insert(param_name.clone(), body_allocator.alloc());
+        }
+
+        let mut body_output = Vec::new();
+        let mut last_reg = None;
+        let (mut body_positions, mut body_pending_calls) = {
+            let mut body_visitor = CompilerVisitor {
+                output: &mut body_output,
+                allocator: &mut body_allocator,
+                positions: self.positions,
+                variables: body_variables,
+                function_start_pcs: self.function_start_pcs,
+                argument_positions: ArgumentPositions::new(),
+                recursive_function_name: Some(name.clone()),
+                pending_calls: Vec::new(),
+                is_top_level: false,
+            };
+
+            for maybe_item in Value::iter_list_rc(args[2].clone()) {
+                let item = body_visitor.label_with_position(maybe_item, Span::point((1, 1)))?;
+                let reg = body_visitor.allocator.current();
+                body_visitor.visit_statement(item)?;
+                last_reg = Some(reg);
+            }
+
+            (body_visitor.argument_positions, body_visitor.pending_calls)
+        };
+
+        let result_reg = match last_reg {
+            Some(reg) => reg,
+            None => {
+                let reg = body_allocator.alloc();
+                body_output.push(LoadImmediate {
+                    dest: reg,
+                    value: Value::Nil,
+                });
+                reg
+            }
+        };
+        body_output.push(Return { src: result_reg });
+
+        let extra = body_allocator.highest_used as code::RegisterOffset + 1
+            - param_names.len() as code::RegisterOffset;
+        if extra > 0 {
+            body_output.insert(0, AllocRegisters { count: extra });
+            body_positions = body_positions
+                .into_iter()
+                .map(|(pc, spans)| (pc + 1, spans))
+                .collect();
+            body_pending_calls = body_pending_calls
+                .into_iter()
+                .map(|(pc, name, span)| (pc + 1, name, span))
+                .collect();
+        }
+
+        let cond = self.allocator.alloc();
+        self.push(LoadImmediate {
+            dest: cond,
+            value: Value::Boolean(true),
+        });
+        self.push(JumpIf {
+            cond,
+            distance: body_output.len() as code::PcOffset,
+        });
+
+        self.function_start_pcs
+            .insert(name, self.output.len() as code::Pc + 1);
+        let body_base = self.output.len() as code::Pc;
+        self.absorb_argument_positions(body_base, body_positions);
+        self.absorb_pending_calls(body_base, body_pending_calls);
+        self.extend(body_output);
+
+        Ok(())
+    }
+
     fn visit_expr(&mut self, expr: Rc<Value>) -> Result<()> {
         use code::Instruction::*;
 
         match &*expr {
-            Value::Integer(_) | Value::Boolean(_) | Value::String(_) | Value::Nil => {
+            Value::Integer(_)
+            | Value::Boolean(_)
+            | Value::String(_)
+            | Value::Nil
+            | Value::Float(_)
+            | Value::Char(_) => {
                 let dest = self.allocator.alloc();
 
                 self.push(LoadImmediate {
@@ -396,19 +1066,93 @@ impl<'o, 'a, 'p> CompilerVisitor<'o, 'a, 'p> {
     }
 }
 
-pub fn compile(program: Rc<Value>, positions: parser::PositionMap) -> Result<Vec<Instruction>> {
+/// Recognizes a `quasiquote` template cell shaped like `(unquote expr)` or
+/// `(unquote-splicing expr)` — a 2-element list headed by `keyword` — returning `expr` if it
+/// matches, or `None` if `value` isn't a cell, isn't headed by `keyword`, or has more than one
+/// argument.
+fn match_unquote_form(value: &Rc<Value>, keyword: &str) -> Option<Rc<Value>> {
+    let (car, cdr) = value.try_as_cell_rc().ok()?;
+    if car.try_as_identifier().ok()?.as_str() != keyword {
+        return None;
+    }
+
+    let (expr, rest) = cdr.try_as_cell_rc().ok()?;
+    if !rest.is_nil() {
+        return None;
+    }
+
+    Some(expr)
+}
+
+/// Patches every placeholder `CallFunction` recorded in `pending_calls` (each `Pc` relative to
+/// `output`, shifted by `shift` the same way `argument_positions` is) with the real target now
+/// that `function_start_pcs` holds every function's start `Pc`, via the `TentativeInstruction`
+/// machinery — an unresolvable label (a recursive call to a name that was never actually defined
+/// as a function) surfaces as `ErrorInternal::UnresolvedLabel`.
+fn resolve_pending_calls(
+    output: &mut [Instruction],
+    function_start_pcs: &HashMap<String, code::Pc>,
+    pending_calls: Vec<(code::Pc, String, Span)>,
+    shift: code::Pc,
+) -> Result<()> {
+    use Instruction::*;
+
+    for (pc, name, span) in pending_calls {
+        let pc = pc + shift;
+
+        let (base, num_args) = match &output[pc] {
+            CallFunction { base, num_args, .. } => (*base, *num_args),
+            _ => unreachable!("pending call doesn't point at a CallFunction placeholder"),
+        };
+
+        let tentative = code::TentativeInstruction::CallFunction {
+            target: function_start_pcs.get(&name).copied(),
+            base: Some(base),
+            num_args: Some(num_args),
+        };
+
+        let (line, column) = span.start;
+        output[pc] = tentative.try_into().map_err(|source| Error {
+            error: ErrorInternal::UnresolvedLabel { source },
+            line,
+            column,
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Compiles `program` to bytecode, returning alongside it the source span of every argument
+/// passed to a builtin, keyed by the `Pc` of the `CallInternal` that passed it — so a runtime
+/// `TypeMismatch` can be traced back to the expression that produced the offending value (see
+/// [`crate::vm::Vm::load_with_positions`]).
+pub fn compile(
+    program: Rc<Value>,
+    positions: parser::PositionMap,
+) -> Result<(Vec<Instruction>, ArgumentPositions)> {
     use Instruction::*;
 
     TypeCheckVisitor::new(&positions).visit_program(program.clone())?;
 
     let mut allocator = RegisterAllocator::new();
     let mut output = Vec::new();
-
-    let num_registers_used = {
-        let mut visitor = CompilerVisitor::new(&mut output, &mut allocator, &positions);
+    let mut function_start_pcs = HashMap::new();
+
+    let (num_registers_used, argument_positions, pending_calls) = {
+        let mut visitor = CompilerVisitor::new(
+            &mut output,
+            &mut allocator,
+            &positions,
+            &mut function_start_pcs,
+            true,
+        );
         visitor.visit_program(program.clone())?;
 
-        visitor.allocator.highest_used as code::RegisterOffset + 1
+        (
+            visitor.allocator.highest_used as code::RegisterOffset + 1,
+            visitor.argument_positions,
+            visitor.pending_calls,
+        )
     };
 
     output.insert(
@@ -418,7 +1162,115 @@ pub fn compile(program: Rc<Value>, positions: parser::PositionMap) -> Result<Vec
         },
     );
 
-    Ok(output)
+    let argument_positions = argument_positions
+        .into_iter()
+        .map(|(pc, spans)| (pc + 1, spans))
+        .collect();
+
+    resolve_pending_calls(&mut output, &function_start_pcs, pending_calls, 1)?;
+
+    Ok((output, argument_positions))
+}
+
+/// Persists the compilation state [`compile`] otherwise starts fresh each time — register
+/// allocation, variable bindings, user-defined functions, and their inferred types — so a caller
+/// that feeds it one top-level form at a time (e.g. a REPL) sees each line's definitions remain
+/// visible to the next.
+pub struct Session {
+    allocator: RegisterAllocator,
+    registers_used: code::RegisterOffset,
+    function_start_pcs: HashMap<String, code::Pc>,
+    variables: HashMap<String, code::RegisterId>,
+    type_variables: HashMap<String, types::Type>,
+    type_functions: HashMap<String, FunctionSignature>,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self {
+            allocator: RegisterAllocator::new(),
+            registers_used: 0,
+            function_start_pcs: HashMap::new(),
+            variables: HashMap::new(),
+            type_variables: HashMap::new(),
+            type_functions: HashMap::new(),
+        }
+    }
+
+    /// Compiles one top-level form against this session's accumulated state instead of starting
+    /// fresh, returning alongside the instructions and argument positions (see [`compile`]) the
+    /// register holding the value of the last statement in `program`, if it had one.
+    pub fn compile_line(
+        &mut self,
+        program: Rc<Value>,
+        positions: parser::PositionMap,
+    ) -> Result<(
+        Vec<Instruction>,
+        ArgumentPositions,
+        Option<code::RegisterId>,
+    )> {
+        use Instruction::*;
+
+        let mut type_visitor = TypeCheckVisitor {
+            variables: core::mem::take(&mut self.type_variables),
+            functions: core::mem::take(&mut self.type_functions),
+            positions: &positions,
+        };
+        type_visitor.visit_block(program.clone())?;
+        self.type_variables = type_visitor.variables;
+        self.type_functions = type_visitor.functions;
+
+        let mut output = Vec::new();
+        let mut last_reg = None;
+        let (argument_positions, pending_calls, variables) = {
+            let mut visitor = CompilerVisitor {
+                output: &mut output,
+                allocator: &mut self.allocator,
+                positions: &positions,
+                variables: core::mem::take(&mut self.variables),
+                function_start_pcs: &mut self.function_start_pcs,
+                argument_positions: ArgumentPositions::new(),
+                recursive_function_name: None,
+                pending_calls: Vec::new(),
+                is_top_level: true,
+            };
+
+            for maybe_item in Value::iter_list_rc(program) {
+                let item = visitor.label_with_position(maybe_item, Span::point((1, 1)))?;
+                let reg = visitor.allocator.current();
+                visitor.visit_statement(item)?;
+                last_reg = Some(reg);
+            }
+
+            (
+                visitor.argument_positions,
+                visitor.pending_calls,
+                visitor.variables,
+            )
+        };
+        self.variables = variables;
+
+        let new_registers_used = self.allocator.highest_used as code::RegisterOffset + 1;
+        let extra = new_registers_used - self.registers_used;
+        self.registers_used = new_registers_used;
+
+        let shift = if extra > 0 {
+            output.insert(0, AllocRegisters { count: extra });
+
+            1
+        } else {
+            0
+        };
+
+        let argument_positions = argument_positions
+            .into_iter()
+            .map(|(pc, spans)| (pc + shift, spans))
+            .collect();
+
+        resolve_pending_calls(&mut output, &self.function_start_pcs, pending_calls, shift)?;
+
+        Ok((output, argument_positions, last_reg))
+    }
 }
 
 #[cfg(test)]
@@ -430,6 +1282,7 @@ mod tests {
         let (program, positions) = parser::parse_implicit_form_list(program.chars()).unwrap();
 
         Ok(compile(program, positions)?
+            .0
             .into_iter()
             .map(|i| format!("{}", i))
             .collect::<Vec<_>>()
@@ -470,6 +1323,21 @@ call debug 0 4;
         Ok(())
     }
 
+    #[test]
+    fn basic_float_and_char() -> Result<()> {
+        snapshot!(
+            compile_display("debug 3.14 'a'")?,
+            "
+alloc 2;
+load 0 3.14;
+load 1 'a';
+call debug 0 2;
+"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn unknown_internal_func_fails() -> Result<()> {
         assert_err_matches_regex!(compile_display("-unknown-"), "Unknown.*line.*1.*1");
@@ -593,4 +1461,139 @@ call + 2 2;
 
         Ok(())
     }
+
+    #[test]
+    fn user_defined_function() -> Result<()> {
+        snapshot!(
+            compile_display(
+                "
+                fn add (a b) { + a b }
+                add 3 4
+                "
+            )?,
+            "
+alloc 3;
+load 0 true;
+jump_if 0 5 ;
+alloc 2;
+copy 2 0;
+copy 3 1;
+call + 2 2;
+ret 2;
+load 1 3;
+load 2 4;
+callf 3 1 2;
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn recursive_function_call() -> Result<()> {
+        snapshot!(
+            compile_display(
+                "
+                fn loop (n) { loop n }
+                loop 1
+                "
+            )?,
+            "
+alloc 2;
+load 0 true;
+jump_if 0 4 ;
+alloc 1;
+copy 1 0;
+callf 3 1 1;
+ret 1;
+load 1 1;
+callf 3 1 1;
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn session_persists_variables_across_lines() -> Result<()> {
+        let mut session = Session::new();
+
+        let (program, positions) = parser::parse_implicit_form_list("set a 1".chars()).unwrap();
+        let (instructions, _, last_reg) = session.compile_line(program, positions)?;
+        snapshot!(
+            instructions
+                .into_iter()
+                .map(|i| format!("{}", i))
+                .collect::<Vec<_>>()
+                .join(";\n")
+                + ";",
+            "
+alloc 1;
+load 0 1;
+"
+        );
+        assert_eq!(last_reg, Some(0));
+
+        let (program, positions) = parser::parse_implicit_form_list("+ a 2".chars()).unwrap();
+        let (instructions, _, last_reg) = session.compile_line(program, positions)?;
+        snapshot!(
+            instructions
+                .into_iter()
+                .map(|i| format!("{}", i))
+                .collect::<Vec<_>>()
+                .join(";\n")
+                + ";",
+            "
+alloc 2;
+copy 1 0;
+load 2 2;
+call + 1 2;
+"
+        );
+        assert_eq!(last_reg, Some(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn function_defined_inside_if_fails() -> Result<()> {
+        assert_err_matches_regex!(
+            compile_display("if true {fn add (a b) { + a b }} {nil}"),
+            "FunctionDefinitionNotAtTopLevel"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn register_allocator_reuses_freed_register_at_the_frontier() {
+        let mut allocator = RegisterAllocator::new();
+
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 2);
+
+        allocator.free(1);
+        allocator.free(2);
+        allocator.current.end = 1;
+
+        // Reuses 1 before ever bumping past it, so `highest_used` doesn't climb any further.
+        assert_eq!(allocator.alloc(), 1);
+        assert_eq!(allocator.alloc(), 2);
+        assert_eq!(allocator.highest_used, 2);
+    }
+
+    #[test]
+    fn register_allocator_does_not_reuse_a_register_below_the_frontier() {
+        let mut allocator = RegisterAllocator::new();
+
+        assert_eq!(allocator.alloc(), 0);
+        assert_eq!(allocator.alloc(), 1);
+
+        allocator.free(0);
+
+        // 0 sits below the current frontier (1), so it's left alone: reusing it here would make
+        // a call's argument window non-contiguous.
+        assert_eq!(allocator.alloc(), 2);
+    }
 }
@@ -0,0 +1,20 @@
+// Copyright (c) Jesse Weaver, 2021
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub use alloc::{format, vec};
+
+pub mod builtins;
+pub mod compiler;
+pub mod parser;
+#[cfg(feature = "std")]
+pub mod tokenizer;
+pub mod types;
+pub mod value;
+pub mod vm;
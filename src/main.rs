@@ -1,3 +1,7 @@
+//! Debug CLI for `scarab::tokenizer`, the standalone legacy tokenizer (see that module's doc
+//! comment): prints the token stream for a string given on the command line. Not a front-end for
+//! running `scarab` programs — use `examples/repl.rs` for that.
+
 extern crate scarab;
 
 use scarab::tokenizer::Tokenizer;
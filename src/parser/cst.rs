@@ -0,0 +1,726 @@
+// Copyright (c) Jesse Weaver, 2021
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A lossless concrete syntax tree ("green tree", after rust-analyzer): every byte of the
+//! original source, including whitespace, newlines and comments, is retained as a token
+//! somewhere in the tree, so `parse_cst(source).to_string() == source` always holds and the
+//! `Value` tree in [`super`] can still be projected from the significant tokens it carries.
+//!
+//! This is a deliberately separate lexer from [`super::tokenizer`]: that tokenizer throws
+//! whitespace away before a token is ever produced, so there's nothing here to wrap; trivia has
+//! to be re-derived straight from the source text instead.
+//!
+//! Line comments start with `;;`, to keep them distinct from the single `;` that already
+//! separates statements within a line (see [`super::tokenizer::Token::Semicolon`]).
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyntaxKind {
+    // Trivia, never significant to the `Value` tree.
+    Whitespace,
+    Comment,
+    // Significant tokens.
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Quote,
+    Newline,
+    Comma,
+    Semicolon,
+    Integer,
+    Float,
+    Char,
+    String,
+    Identifier,
+    /// A token the lexer couldn't make sense of (e.g. an unterminated char literal); kept so the
+    /// tree stays lossless even over malformed input.
+    Error,
+    // Nodes.
+    /// A `(...)`, `[...]` or `{...}` group, including its delimiter tokens; which kind of
+    /// delimiter it is can be read off its first child.
+    List,
+    Root,
+}
+
+impl SyntaxKind {
+    pub fn is_trivia(self) -> bool {
+        matches!(self, SyntaxKind::Whitespace | SyntaxKind::Comment)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    pub kind: SyntaxKind,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GreenElement {
+    Token(GreenToken),
+    Node(Rc<GreenNode>),
+}
+
+impl GreenElement {
+    fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Token(t) => t.text.len(),
+            GreenElement::Node(n) => n.text_len(),
+        }
+    }
+
+    fn write_text(&self, out: &mut String) {
+        match self {
+            GreenElement::Token(t) => out.push_str(&t.text),
+            GreenElement::Node(n) => n.write_text(out),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GreenNode {
+    pub kind: SyntaxKind,
+    pub children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    fn text_len(&self) -> usize {
+        self.children.iter().map(GreenElement::text_len).sum()
+    }
+
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            child.write_text(out);
+        }
+    }
+}
+
+/// A `GreenNode` paired with its byte offset in the original source. Analogous to
+/// rust-analyzer's "red" tree: the green tree itself carries no position information, so offsets
+/// are computed on the way down from the root instead of being stored redundantly at every node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CstNode {
+    green: Rc<GreenNode>,
+    offset: usize,
+}
+
+impl CstNode {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind
+    }
+
+    pub fn range(&self) -> core::ops::Range<usize> {
+        self.offset..(self.offset + self.green.text_len())
+    }
+
+    /// The child nodes of this node, in source order; trivia and significant tokens are skipped,
+    /// as they're reachable through [`CstNode::tokens`] instead.
+    pub fn children(&self) -> Vec<CstNode> {
+        let mut offset = self.offset;
+        let mut result = vec![];
+
+        for child in &self.green.children {
+            if let GreenElement::Node(n) = child {
+                result.push(CstNode {
+                    green: n.clone(),
+                    offset,
+                });
+            }
+
+            offset += child.text_len();
+        }
+
+        result
+    }
+
+    /// The tokens directly under this node, including trivia, in source order.
+    pub fn tokens(&self) -> impl Iterator<Item = &GreenToken> {
+        self.green.children.iter().filter_map(|c| match c {
+            GreenElement::Token(t) => Some(t),
+            GreenElement::Node(_) => None,
+        })
+    }
+}
+
+impl core::fmt::Display for CstNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut s = String::new();
+        self.green.write_text(&mut s);
+        write!(f, "{}", s)
+    }
+}
+
+fn is_token_boundary(c: char) -> bool {
+    matches!(c, '(' | ')' | '[' | ']' | '{' | '}' | '\'' | '"' | '\n' | ',' | ';')
+        || c.is_ascii_whitespace()
+}
+
+/// Classifies a run of non-boundary characters as a number or an identifier, mirroring
+/// `tokenizer::Tokenizer::tokenize_number`'s base/float heuristics closely enough to label the
+/// token correctly; unlike that tokenizer, this never needs to actually parse the value out.
+fn classify_run(text: &str) -> SyntaxKind {
+    // An unsigned leading `.` followed by a digit (`.5`) is a float; `-.5` stays an identifier.
+    if text.starts_with('.') && text[1..].starts_with(|c: char| c.is_ascii_digit()) {
+        return SyntaxKind::Float;
+    }
+
+    let digits_start = if let Some(rest) = text.strip_prefix('-') {
+        rest
+    } else {
+        text
+    };
+
+    if !digits_start.starts_with(|c: char| c.is_ascii_digit()) {
+        return SyntaxKind::Identifier;
+    }
+
+    let is_hex_or_binary = digits_start.starts_with("0x")
+        || digits_start.starts_with("0b")
+        || digits_start.starts_with("0o");
+
+    if !is_hex_or_binary && (digits_start.contains('.') || digits_start.contains(['e', 'E'])) {
+        SyntaxKind::Float
+    } else {
+        SyntaxKind::Integer
+    }
+}
+
+type Chars<'a> = core::iter::Peekable<core::str::CharIndices<'a>>;
+
+fn lex_string(source: &str, chars: &mut Chars, start: usize) -> (SyntaxKind, usize) {
+    chars.next();
+    let mut end = start + 1;
+
+    loop {
+        match chars.next() {
+            Some((_, '\\')) => {
+                if let Some((i, c)) = chars.next() {
+                    end = i + c.len_utf8();
+                }
+            }
+            Some((i, '"')) => {
+                end = i + 1;
+                break;
+            }
+            Some((i, c)) => end = i + c.len_utf8(),
+            None => break,
+        }
+    }
+
+    let _ = source;
+    (SyntaxKind::String, end)
+}
+
+fn lex_raw_string(chars: &mut Chars, start: usize) -> (SyntaxKind, usize) {
+    chars.next(); // 'r'
+
+    let mut hashes = 0;
+    while let Some((_, '#')) = chars.peek().copied() {
+        chars.next();
+        hashes += 1;
+    }
+
+    chars.next(); // opening '"'
+    let mut end = start;
+
+    loop {
+        match chars.next() {
+            Some((i, '"')) => {
+                let mut matched = 0;
+                let mut lookahead = chars.clone();
+                while matched < hashes {
+                    match lookahead.peek().copied() {
+                        Some((_, '#')) => {
+                            lookahead.next();
+                            matched += 1;
+                        }
+                        _ => break,
+                    }
+                }
+
+                if matched == hashes {
+                    for _ in 0..hashes {
+                        chars.next();
+                    }
+                    end = i + 1 + hashes;
+                    break;
+                } else {
+                    end = i + 1;
+                }
+            }
+            Some((i, c)) => end = i + c.len_utf8(),
+            None => break,
+        }
+    }
+
+    (SyntaxKind::String, end)
+}
+
+/// Disambiguates a char literal (`'a'`, `'\n'`) from a quoted value (`'abc`, `'(1 2)`) the same
+/// way `tokenizer::Tokenizer::tokenize_quote_or_char` does: a char literal never contains
+/// whitespace between its quotes, so anything else falls back to a bare `Quote` token and leaves
+/// the following characters untouched for the next iteration to lex normally.
+fn lex_quote_or_char(chars: &mut Chars, start: usize) -> (SyntaxKind, usize) {
+    chars.next(); // opening '\''
+
+    let next = match chars.peek().copied() {
+        Some((_, c)) if !c.is_ascii_whitespace() => c,
+        _ => return (SyntaxKind::Quote, start + 1),
+    };
+
+    if next == '\\' {
+        chars.next();
+
+        if let Some((_, 'u')) = chars.next() {
+            if let Some((_, '{')) = chars.peek().copied() {
+                chars.next();
+                while let Some((_, c)) = chars.next() {
+                    if c == '}' {
+                        break;
+                    }
+                }
+            }
+        }
+
+        return match chars.next() {
+            Some((i, '\'')) => (SyntaxKind::Char, i + 1),
+            Some((i, c)) => (SyntaxKind::Error, i + c.len_utf8()),
+            None => (SyntaxKind::Error, start + 1),
+        };
+    }
+
+    let mut lookahead = chars.clone();
+    lookahead.next();
+
+    match lookahead.next() {
+        Some((i, '\'')) => {
+            chars.next();
+            chars.next();
+            (SyntaxKind::Char, i + 1)
+        }
+        _ => (SyntaxKind::Quote, start + 1),
+    }
+}
+
+fn lex(source: &str) -> Vec<GreenToken> {
+    let mut chars: Chars = source.char_indices().peekable();
+    let mut tokens = vec![];
+
+    while let Some(&(start, c)) = chars.peek() {
+        let (kind, end) = match c {
+            '(' => {
+                chars.next();
+                (SyntaxKind::LParen, start + 1)
+            }
+            ')' => {
+                chars.next();
+                (SyntaxKind::RParen, start + 1)
+            }
+            '[' => {
+                chars.next();
+                (SyntaxKind::LBracket, start + 1)
+            }
+            ']' => {
+                chars.next();
+                (SyntaxKind::RBracket, start + 1)
+            }
+            '{' => {
+                chars.next();
+                (SyntaxKind::LBrace, start + 1)
+            }
+            '}' => {
+                chars.next();
+                (SyntaxKind::RBrace, start + 1)
+            }
+            ',' => {
+                chars.next();
+                (SyntaxKind::Comma, start + 1)
+            }
+            '\n' => {
+                chars.next();
+                (SyntaxKind::Newline, start + 1)
+            }
+            ';' => {
+                chars.next();
+
+                if let Some((_, ';')) = chars.peek().copied() {
+                    chars.next();
+                    let mut end = start + 2;
+
+                    while let Some(&(i, c2)) = chars.peek() {
+                        if c2 == '\n' {
+                            break;
+                        }
+                        end = i + c2.len_utf8();
+                        chars.next();
+                    }
+
+                    (SyntaxKind::Comment, end)
+                } else {
+                    (SyntaxKind::Semicolon, start + 1)
+                }
+            }
+            ' ' | '\t' | '\r' => {
+                let mut end = start;
+
+                while let Some(&(i, c2)) = chars.peek() {
+                    if c2 == ' ' || c2 == '\t' || c2 == '\r' {
+                        end = i + 1;
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+
+                (SyntaxKind::Whitespace, end)
+            }
+            '"' => lex_string(source, &mut chars, start),
+            'r' if matches!(
+                { let mut l = chars.clone(); l.next(); l.peek().copied() },
+                Some((_, '"')) | Some((_, '#'))
+            ) =>
+            {
+                lex_raw_string(&mut chars, start)
+            }
+            '\'' => lex_quote_or_char(&mut chars, start),
+            _ => {
+                chars.next();
+                let mut end = start + c.len_utf8();
+
+                while let Some(&(i, c2)) = chars.peek() {
+                    if is_token_boundary(c2) {
+                        break;
+                    }
+                    end = i + c2.len_utf8();
+                    chars.next();
+                }
+
+                (classify_run(&source[start..end]), end)
+            }
+        };
+
+        tokens.push(GreenToken {
+            kind,
+            text: source[start..end].to_string(),
+        });
+    }
+
+    tokens
+}
+
+fn matching_close(open: SyntaxKind) -> SyntaxKind {
+    match open {
+        SyntaxKind::LParen => SyntaxKind::RParen,
+        SyntaxKind::LBracket => SyntaxKind::RBracket,
+        SyntaxKind::LBrace => SyntaxKind::RBrace,
+        _ => unreachable!("only called with an opening-delimiter kind"),
+    }
+}
+
+/// Groups a flat token stream into `List` nodes by matching delimiters. An unterminated list
+/// (one that runs off the end of input before its closing delimiter) still ends up with all of
+/// its tokens somewhere in the tree, just without a closing token among them — the tree stays
+/// lossless even though it's no longer balanced.
+fn build_children(
+    iter: &mut core::iter::Peekable<alloc::vec::IntoIter<GreenToken>>,
+    close: Option<SyntaxKind>,
+) -> Vec<GreenElement> {
+    let mut children = vec![];
+
+    while let Some(token) = iter.peek() {
+        if Some(token.kind) == close {
+            children.push(GreenElement::Token(iter.next().unwrap()));
+            return children;
+        }
+
+        match token.kind {
+            SyntaxKind::LParen | SyntaxKind::LBracket | SyntaxKind::LBrace => {
+                let open_kind = token.kind;
+                let mut inner = vec![GreenElement::Token(iter.next().unwrap())];
+                inner.extend(build_children(iter, Some(matching_close(open_kind))));
+
+                children.push(GreenElement::Node(Rc::new(GreenNode {
+                    kind: SyntaxKind::List,
+                    children: inner,
+                })));
+            }
+            _ => children.push(GreenElement::Token(iter.next().unwrap())),
+        }
+    }
+
+    children
+}
+
+/// Parses `source` into a lossless concrete syntax tree: `parse_cst(source).to_string()` always
+/// reproduces `source` exactly, byte for byte, including whitespace, newlines and comments.
+pub fn parse_cst(source: &str) -> CstNode {
+    let tokens = lex(source);
+    let mut iter = tokens.into_iter().peekable();
+    let children = build_children(&mut iter, None);
+
+    CstNode {
+        green: Rc::new(GreenNode {
+            kind: SyntaxKind::Root,
+            children,
+        }),
+        offset: 0,
+    }
+}
+
+fn is_terminated(node: &GreenNode) -> bool {
+    if node.kind != SyntaxKind::List {
+        return true;
+    }
+
+    let open_kind = match node.children.first() {
+        Some(GreenElement::Token(t)) => t.kind,
+        _ => return false,
+    };
+
+    matches!(
+        node.children.last(),
+        Some(GreenElement::Token(t)) if t.kind == matching_close(open_kind)
+    )
+}
+
+/// A single text replacement to reapply to a previously parsed tree: `old_len` bytes starting at
+/// `offset` are replaced with `new_text`, exactly as in tree-sitter's edit API.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    pub offset: usize,
+    pub old_len: usize,
+    pub new_text: String,
+}
+
+impl Edit {
+    fn old_range(&self) -> core::ops::Range<usize> {
+        self.offset..(self.offset + self.old_len)
+    }
+}
+
+/// Reparses `old_source` with `edit` applied, reusing as much of `old` as possible instead of
+/// re-tokenizing from scratch: it finds the smallest `List` node whose span fully contains the
+/// edit, reparses only that node's (spliced) text, and splices the result into a clone of `old`.
+/// Nodes after the edit don't need their stored positions shifted by hand — unlike a tree that
+/// caches absolute offsets, a `CstNode`'s offset is always derived from its ancestors' actual
+/// child lengths at query time (see [`CstNode::range`]), so once the shorter or longer subtree is
+/// spliced in, every sibling after it reports the right position for free.
+///
+/// Falls back to reparsing the whole document — equivalent to calling [`parse_cst`] directly —
+/// when the edit crosses a top-level form boundary or lands inside an unterminated list, since
+/// neither case leaves a subtree that can be proven unaffected by the edit.
+pub fn reparse(old: &CstNode, old_source: &str, edit: &Edit) -> (CstNode, String) {
+    let mut new_source = old_source.to_string();
+    new_source.replace_range(edit.old_range(), &edit.new_text);
+
+    match try_reparse_subtree(&old.green, old.offset, old_source, edit) {
+        Some(green) => (
+            CstNode {
+                green: Rc::new(green),
+                offset: 0,
+            },
+            new_source,
+        ),
+        None => (parse_cst(&new_source), new_source),
+    }
+}
+
+fn try_reparse_subtree(
+    node: &Rc<GreenNode>,
+    node_offset: usize,
+    old_source: &str,
+    edit: &Edit,
+) -> Option<GreenNode> {
+    let node_range = node_offset..(node_offset + node.text_len());
+
+    if edit.offset < node_range.start || edit.offset + edit.old_len > node_range.end {
+        return None;
+    }
+
+    if is_terminated(node) {
+        let mut child_offset = node_offset;
+
+        for child in &node.children {
+            let child_len = child.text_len();
+
+            if let GreenElement::Node(child_node) = child {
+                if let Some(new_child) = try_reparse_subtree(child_node, child_offset, old_source, edit)
+                {
+                    let mut children = node.children.clone();
+                    let index = children
+                        .iter()
+                        .position(|c| matches!(c, GreenElement::Node(n) if Rc::ptr_eq(n, child_node)))
+                        .unwrap();
+                    children[index] = GreenElement::Node(Rc::new(new_child));
+
+                    return Some(GreenNode {
+                        kind: node.kind,
+                        children,
+                    });
+                }
+            }
+
+            child_offset += child_len;
+        }
+    }
+
+    // No child fully contains the edit, or this node is unterminated. Reparsing "this node" is
+    // only useful when it's a terminated list smaller than the whole document; otherwise this is
+    // the root, and the caller's full-document fallback is no more work than reparsing here.
+    if node.kind != SyntaxKind::List || !is_terminated(node) {
+        return None;
+    }
+
+    let mut span_text = old_source[node_range.clone()].to_string();
+    let local_offset = edit.offset - node_range.start;
+    span_text.replace_range(local_offset..(local_offset + edit.old_len), &edit.new_text);
+
+    match parse_cst(&span_text).green.children.as_slice() {
+        [GreenElement::Node(list)] if list.kind == SyntaxKind::List => Some((**list).clone()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use k9::snapshot;
+
+    #[test]
+    fn round_trips_simple_list() {
+        let source = "(+ 123\n456) ;; comment";
+        let cst = parse_cst(source);
+
+        assert_eq!(cst.to_string(), source);
+    }
+
+    #[test]
+    fn round_trips_nested_and_unterminated_lists() {
+        for source in [
+            "(a (b [c + d]) {e; f})",
+            "  (a\n  b) ; not a comment, an empty statement\n",
+            "(a (b",
+            "'a' '\\n' 'abc \"str\\\"ing\" r#\"raw \" string\"#",
+        ] {
+            assert_eq!(parse_cst(source).to_string(), source);
+        }
+    }
+
+    #[test]
+    fn top_level_node_is_root_containing_one_list() {
+        let cst = parse_cst("(+ 1 2)");
+
+        snapshot!(format!("{:?}", cst.kind()), "Root");
+
+        let children = cst.children();
+        assert_eq!(children.len(), 1);
+        snapshot!(format!("{:?}", children[0].kind()), "List");
+        assert_eq!(children[0].range(), 0..7);
+    }
+
+    #[test]
+    fn trivia_is_retained_alongside_significant_tokens() {
+        let cst = parse_cst("(+ 1 2) ;; trailing\n");
+        let list = &cst.children()[0];
+
+        let kinds: Vec<_> = list.tokens().map(|t| t.kind).collect();
+        snapshot!(
+            format!("{:?}", kinds),
+            "[LParen, Identifier, Whitespace, Integer, Whitespace, Integer, RParen]"
+        );
+
+        let root_kinds: Vec<_> = cst.tokens().map(|t| t.kind).collect();
+        assert_eq!(
+            root_kinds,
+            vec![SyntaxKind::Whitespace, SyntaxKind::Comment, SyntaxKind::Newline]
+        );
+    }
+
+    fn assert_reparse_matches_full(old_source: &str, edit: Edit) {
+        let old = parse_cst(old_source);
+        let (incremental, new_source) = reparse(&old, old_source, &edit);
+        let full = parse_cst(&new_source);
+
+        assert_eq!(incremental.to_string(), new_source);
+        assert_eq!(incremental, full);
+    }
+
+    #[test]
+    fn single_char_insert_within_one_list_matches_full_reparse() {
+        assert_reparse_matches_full(
+            "(foo 1 2) (bar 3 4)",
+            Edit {
+                offset: 6,
+                old_len: 0,
+                new_text: "9".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn single_char_delete_within_one_list_matches_full_reparse() {
+        assert_reparse_matches_full(
+            "(foo 123 2) (bar 3 4)",
+            Edit {
+                offset: 7,
+                old_len: 1,
+                new_text: "".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn edit_crossing_top_level_forms_matches_full_reparse() {
+        assert_reparse_matches_full(
+            "(foo 1 2) (bar 3 4)",
+            Edit {
+                offset: 8,
+                old_len: 3,
+                new_text: "".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn edit_inside_unterminated_list_matches_full_reparse() {
+        assert_reparse_matches_full(
+            "(foo 1 (bar 2",
+            Edit {
+                offset: 12,
+                old_len: 0,
+                new_text: "3".to_string(),
+            },
+        );
+    }
+
+    #[test]
+    fn reparse_reuses_sibling_subtree_unchanged() {
+        let old_source = "(foo 1 2) (bar 3 4)";
+        let old = parse_cst(old_source);
+        let (incremental, _) = reparse(
+            &old,
+            old_source,
+            &Edit {
+                offset: 6,
+                old_len: 0,
+                new_text: "9".to_string(),
+            },
+        );
+
+        let old_second_child = old.children()[1].green.clone();
+        let new_second_child = incremental.children()[1].green.clone();
+
+        assert!(Rc::ptr_eq(&old_second_child, &new_second_child));
+    }
+}
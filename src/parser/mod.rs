@@ -4,14 +4,24 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod cst;
 mod tokenizer;
 
-use std::rc::Rc;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
 use thiserror::Error;
 
-use self::tokenizer::{tokenize, Token, Tokenizer};
+use self::tokenizer::{tokenize, Tokenizer};
 use crate::value::{self, Value};
-use result_at::{Position, Reader, ResultAt, ResultAt::*};
+use result_at::{Reader, ResultAt, ResultAt::*, Span};
+
+// `Parser` only ever drives the owned, streaming `tokenize()` (never `tokenize_str()`'s
+// buffer-borrowing tokens), so every `Token` it sees is `'static`; this alias lets the rest of
+// this module keep writing the bare `Token` it did before tokens grew a lifetime.
+type Token = self::tokenizer::Token<'static>;
 
 #[derive(Error, Clone, Debug, Eq, PartialEq)]
 pub enum ErrorInternal {
@@ -46,20 +56,31 @@ pub struct Error {
 }
 
 impl Error {
-    fn from_internal_at(error: ErrorInternal, at: (usize, usize)) -> Self {
-        let (line, column) = at;
+    fn from_internal_at(error: ErrorInternal, at: Span) -> Self {
+        let (line, column) = at.start;
         Error {
             error,
             line,
             column,
         }
     }
+
+    /// True if this error only means the input ended before a form was finished (an unclosed
+    /// `(`, `[`, or `{`, or a value cut off mid-parse) rather than a genuine syntax error — so a
+    /// caller reading input incrementally (a REPL) should keep reading more lines and retry
+    /// instead of reporting failure.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.error,
+            ErrorInternal::Eof | ErrorInternal::UnterminatedList
+        )
+    }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
         write!(
             f,
             "{} (at line {}, column {})",
@@ -68,7 +89,7 @@ impl std::fmt::Display for Error {
     }
 }
 
-type Result<T> = std::result::Result<T, Error>;
+type Result<T> = core::result::Result<T, Error>;
 type IResultAt<T> = ResultAt<T, ErrorInternal>;
 
 fn result_from_result_at<T>(result_at: IResultAt<T>) -> Result<T> {
@@ -79,10 +100,10 @@ fn result_from_result_at<T>(result_at: IResultAt<T>) -> Result<T> {
     }
 }
 
-pub type PositionMap = value::ContextMap<Position>;
+pub type PositionMap = value::ContextMap<Span>;
 
 pub struct Parser<I: Iterator<Item = char>> {
-    input: Reader<Tokenizer<I>>,
+    input: Reader<Tokenizer<'static, I>>,
     positions: PositionMap,
 }
 
@@ -116,6 +137,20 @@ macro_rules! expect_match_at {
 }
 
 impl<I: Iterator<Item = char>> Parser<I> {
+    /// Builds a parser over `input` for repeated use, e.g. via [`Self::values`], rather than the
+    /// one-shot usage of the free functions below.
+    pub fn new(input: I) -> Self {
+        Parser {
+            input: tokenize(input),
+            positions: value::ContextMap::new(),
+        }
+    }
+
+    /// The positions recorded for every value parsed so far.
+    pub fn positions(&self) -> &PositionMap {
+        &self.positions
+    }
+
     fn elements_to_list(elements: Vec<Rc<Value>>) -> Rc<Value> {
         Rc::new(
             elements
@@ -156,7 +191,7 @@ impl<I: Iterator<Item = char>> Parser<I> {
     fn parse_list(
         &mut self,
         terminator_predicate: impl Fn(IResultAt<&Token>) -> IResultAt<bool>,
-        at: (usize, usize),
+        at: Span,
     ) -> IResultAt<Rc<Value>> {
         let mut elements = vec![];
 
@@ -175,11 +210,11 @@ impl<I: Iterator<Item = char>> Parser<I> {
         OkAt(Self::elements_to_list(elements), at)
     }
 
-    fn parse_operator_list(&mut self, at: (usize, usize)) -> IResultAt<Rc<Value>> {
+    fn parse_operator_list(&mut self, at: Span) -> IResultAt<Rc<Value>> {
         let (first, _) = self.parse_value()?;
 
         let (operator, operator_at) = expect_match_at!( self.next()?, {
-            (Token::Identifier(i), at) => (i, at),
+            (Token::Identifier(i), at) => (i.into_owned(), at),
         });
 
         let operator_value = Rc::new(Value::Identifier(value::identifier(operator.clone())));
@@ -192,7 +227,7 @@ impl<I: Iterator<Item = char>> Parser<I> {
         while *self.peek_or(ErrorInternal::UnterminatedList)?.0 != Token::RBracket {
             let (next, at) = self.next()?;
             let next_operator = expect_match!( (next, at), {
-                Token::Identifier(i) => i,
+                Token::Identifier(i) => i.into_owned(),
             });
 
             if next_operator != operator {
@@ -215,7 +250,7 @@ impl<I: Iterator<Item = char>> Parser<I> {
     fn parse_form_list_item(
         &mut self,
         terminator_predicate: impl Fn(IResultAt<&Token>) -> IResultAt<bool>,
-        at: (usize, usize),
+        at: Span,
     ) -> IResultAt<Rc<Value>> {
         let (list, _) = self.parse_list(terminator_predicate, at)?;
 
@@ -226,7 +261,7 @@ impl<I: Iterator<Item = char>> Parser<I> {
         OkAt(list, at)
     }
 
-    fn parse_form_list(&mut self, mut at: (usize, usize)) -> IResultAt<Rc<Value>> {
+    fn parse_form_list(&mut self, mut at: Span) -> IResultAt<Rc<Value>> {
         let mut lists = vec![];
 
         loop {
@@ -256,7 +291,7 @@ impl<I: Iterator<Item = char>> Parser<I> {
 
     pub fn parse_implicit_form_list(&mut self) -> IResultAt<Rc<Value>> {
         let mut lists = vec![];
-        let mut at = (1, 1);
+        let mut at = Span::point((1, 1));
 
         loop {
             let next_at = match self.input.peek() {
@@ -287,13 +322,15 @@ impl<I: Iterator<Item = char>> Parser<I> {
             .and_then_at(|t, at| {
                 expect_match!( (t, at), {
                     Token::Integer(i) => OkAt(Rc::new(Value::Integer(i)), at),
-                    Token::String(s) => OkAt(Rc::new(Value::String(s)), at),
+                    Token::Float(f) => OkAt(Rc::new(Value::Float(f.into())), at),
+                    Token::Char(c) => OkAt(Rc::new(Value::Char(c)), at),
+                    Token::String(s) => OkAt(Rc::new(Value::String(s.into_owned())), at),
                     Token::Identifier(i) => {
-                        OkAt(match i.as_str() {
+                        OkAt(match i.as_ref() {
                             "nil" => Rc::new(Value::Nil),
                             "true" => Rc::new(Value::Boolean(true)),
                             "false" => Rc::new(Value::Boolean(false)),
-                            _ => Rc::new(Value::Identifier(i)),
+                            _ => Rc::new(Value::Identifier(i.into_owned())),
                         }, at)
                     },
                     Token::LParen => {
@@ -312,6 +349,331 @@ impl<I: Iterator<Item = char>> Parser<I> {
                 OkAt(t, at)
             })
     }
+
+    /// The FIRST set of [`Self::parse_value`]: true if the parser is currently sitting on a token
+    /// that could validly start a value, false if it's at EOF or on a token `parse_value` is
+    /// certain to reject. Lets a caller ask "can a value start here?" without the committal,
+    /// error-returning behavior of actually calling `parse_value`.
+    pub fn at_value_start(&mut self) -> bool {
+        matches!(
+            self.peek(),
+            OkAt(
+                Token::Integer(_)
+                    | Token::Float(_)
+                    | Token::Char(_)
+                    | Token::String(_)
+                    | Token::Identifier(_)
+                    | Token::LParen
+                    | Token::LBracket
+                    | Token::LBrace
+                    | Token::Quote,
+                _
+            )
+        )
+    }
+
+    /// Turns this parser into a reusable streaming front end: each call advances past the next
+    /// top-level value and yields it, stopping cleanly (no further items, no error) at EOF rather
+    /// than erroring the way a single `parse_value()` call would. This lets a REPL feed
+    /// characters in as they arrive and evaluate each complete form as soon as it's available, or
+    /// lets an embedder splice scarab expressions into host input without pre-splitting on
+    /// newlines.
+    pub fn values(&mut self) -> impl Iterator<Item = Result<Rc<Value>>> + '_ {
+        core::iter::from_fn(move || {
+            if matches!(self.peek(), NoneAt(_)) {
+                return None;
+            }
+
+            Some(result_from_result_at(self.parse_value()))
+        })
+    }
+
+    /// The set of tokens `*_recovering` functions resynchronize on: any list terminator, a
+    /// statement separator, or a newline. `next()`/`peek()` always treat EOF as an implicit
+    /// member of this set too.
+    fn is_recovery_anchor(t: &Token) -> bool {
+        matches!(
+            t,
+            Token::RParen | Token::RBracket | Token::RBrace | Token::Semicolon | Token::Newline
+        )
+    }
+
+    fn consume_if(&mut self, token: &Token) -> bool {
+        let matches = matches!(self.input.peek().as_ref(), OkAt(t, _) if t == token);
+
+        if matches {
+            self.input.next();
+        }
+
+        matches
+    }
+
+    /// Skips tokens until the next one is a recovery anchor (see [`Self::is_recovery_anchor`]),
+    /// `extra_terminator`, or EOF, without consuming that token. Used to resynchronize after a
+    /// diagnostic has been recorded for a token `expect_match!` would otherwise have bailed on.
+    fn bump_to_recovery(&mut self, extra_terminator: impl Fn(&Token) -> bool) {
+        loop {
+            match self.input.peek().as_ref() {
+                OkAt(t, _) if Self::is_recovery_anchor(t) || extra_terminator(t) => return,
+                OkAt(_, _) => {
+                    self.input.next();
+                }
+                ErrAt(_, _) => {
+                    self.input.next();
+                }
+                NoneAt(_) => return,
+            }
+        }
+    }
+
+    /// Recovery variant of [`Self::parse_value`]: instead of bailing on the first unexpected or
+    /// erroring token, records it in `diagnostics`, resynchronizes at the next recovery anchor,
+    /// and returns a [`Value::Error`] placeholder so the caller can keep going.
+    fn parse_value_recovering(&mut self, diagnostics: &mut Vec<Error>) -> Rc<Value> {
+        let (token, at) = match self.next() {
+            OkAt(t, at) => (t, at),
+            ErrAt(e, at) => {
+                diagnostics.push(Error::from_internal_at(e, at));
+                self.bump_to_recovery(|_| false);
+
+                return Rc::new(Value::Error);
+            }
+            NoneAt(at) => {
+                diagnostics.push(Error::from_internal_at(ErrorInternal::Eof, at));
+
+                return Rc::new(Value::Error);
+            }
+        };
+
+        let value = match token {
+            Token::Integer(i) => Rc::new(Value::Integer(i)),
+            Token::Float(f) => Rc::new(Value::Float(f.into())),
+            Token::Char(c) => Rc::new(Value::Char(c)),
+            Token::String(s) => Rc::new(Value::String(s.into_owned())),
+            Token::Identifier(i) => Rc::new(match i.as_ref() {
+                "nil" => Value::Nil,
+                "true" => Value::Boolean(true),
+                "false" => Value::Boolean(false),
+                _ => Value::Identifier(i.into_owned()),
+            }),
+            Token::LParen => {
+                let result =
+                    self.parse_list_recovering(|t| *t == Token::RParen, false, diagnostics);
+                self.consume_if(&Token::RParen);
+
+                result
+            }
+            Token::LBracket => self.parse_operator_list_recovering(diagnostics),
+            Token::LBrace => match self.parse_form_list(at) {
+                OkAt(v, _) => v,
+                ErrAt(e, err_at) => {
+                    diagnostics.push(Error::from_internal_at(e, err_at));
+                    self.bump_to_recovery(|_| false);
+
+                    Rc::new(Value::Error)
+                }
+                NoneAt(err_at) => {
+                    diagnostics.push(Error::from_internal_at(ErrorInternal::Eof, err_at));
+
+                    Rc::new(Value::Error)
+                }
+            },
+            Token::Quote => Rc::new(Value::Quoted(self.parse_value_recovering(diagnostics))),
+            other => {
+                diagnostics.push(Error::from_internal_at(
+                    ErrorInternal::UnexpectedToken(other),
+                    at,
+                ));
+                self.bump_to_recovery(|_| false);
+
+                Rc::new(Value::Error)
+            }
+        };
+
+        self.positions.insert(&value, at);
+
+        value
+    }
+
+    /// Recovery variant of [`Self::parse_list`]. Never consumes `is_own_terminator`'s token
+    /// itself, leaving that to the caller (same contract as `parse_list`) so braces can
+    /// distinguish "end of this line" from "end of the whole form list". `UnterminatedList` is
+    /// reported at wherever the list actually broke off (EOF, or an enclosing anchor), matching
+    /// how the non-recovering parser reports it.
+    fn parse_list_recovering(
+        &mut self,
+        is_own_terminator: impl Fn(&Token) -> bool,
+        eof_terminates: bool,
+        diagnostics: &mut Vec<Error>,
+    ) -> Rc<Value> {
+        let mut elements = vec![];
+
+        loop {
+            match self.peek_with_newlines() {
+                OkAt(t, _) if is_own_terminator(t) => break,
+                NoneAt(eof_at) => {
+                    if !eof_terminates {
+                        diagnostics
+                            .push(Error::from_internal_at(ErrorInternal::UnterminatedList, eof_at));
+                    }
+
+                    break;
+                }
+                ErrAt(e, err_at) => {
+                    diagnostics.push(Error::from_internal_at(e, err_at));
+                    self.input.next();
+                }
+                OkAt(t, anchor_at) if Self::is_recovery_anchor(t) => {
+                    // An enclosing terminator showed up before ours: this list was never closed.
+                    diagnostics
+                        .push(Error::from_internal_at(ErrorInternal::UnterminatedList, anchor_at));
+
+                    break;
+                }
+                _ => elements.push(self.parse_value_recovering(diagnostics)),
+            }
+        }
+
+        Self::elements_to_list(elements)
+    }
+
+    /// Resynchronizes an operator list after a bad operator: skips to the list's own `]` (or an
+    /// enclosing recovery anchor) and consumes it if found, recording `UnterminatedList` (at
+    /// wherever resynchronization stopped) if not.
+    fn recover_operator_list(&mut self, diagnostics: &mut Vec<Error>) {
+        self.bump_to_recovery(|t| *t == Token::RBracket);
+
+        if !self.consume_if(&Token::RBracket) {
+            let at = match self.input.peek() {
+                OkAt(_, at) | ErrAt(_, at) | NoneAt(at) => *at,
+            };
+            diagnostics.push(Error::from_internal_at(ErrorInternal::UnterminatedList, at));
+        }
+    }
+
+    /// Recovery variant of [`Self::parse_operator_list`]: a missing or mismatched operator is
+    /// recorded as a diagnostic and the list is resynchronized at its closing `]`, rather than
+    /// aborting the whole parse.
+    fn parse_operator_list_recovering(&mut self, diagnostics: &mut Vec<Error>) -> Rc<Value> {
+        let first = self.parse_value_recovering(diagnostics);
+
+        let (operator, operator_at) = match self.next() {
+            OkAt(Token::Identifier(i), operator_at) => (i.into_owned(), operator_at),
+            OkAt(other, op_at) => {
+                diagnostics.push(Error::from_internal_at(
+                    ErrorInternal::UnexpectedToken(other),
+                    op_at,
+                ));
+                self.recover_operator_list(diagnostics);
+
+                return Self::elements_to_list(vec![first]);
+            }
+            ErrAt(e, op_at) => {
+                diagnostics.push(Error::from_internal_at(e, op_at));
+                self.recover_operator_list(diagnostics);
+
+                return Self::elements_to_list(vec![first]);
+            }
+            NoneAt(op_at) => {
+                diagnostics.push(Error::from_internal_at(ErrorInternal::UnterminatedList, op_at));
+
+                return Self::elements_to_list(vec![first]);
+            }
+        };
+
+        let operator_value = Rc::new(Value::Identifier(value::identifier(operator.clone())));
+        self.positions.insert(&operator_value, operator_at);
+
+        let second = self.parse_value_recovering(diagnostics);
+
+        let mut elements = vec![operator_value, first, second];
+
+        loop {
+            if self.consume_if(&Token::RBracket) {
+                break;
+            }
+
+            let (next, next_at) = match self.next() {
+                OkAt(t, next_at) => (t, next_at),
+                ErrAt(e, next_at) => {
+                    diagnostics.push(Error::from_internal_at(e, next_at));
+                    self.recover_operator_list(diagnostics);
+
+                    break;
+                }
+                NoneAt(next_at) => {
+                    diagnostics
+                        .push(Error::from_internal_at(ErrorInternal::UnterminatedList, next_at));
+
+                    break;
+                }
+            };
+
+            let next_operator = match next {
+                Token::Identifier(i) => i.into_owned(),
+                other => {
+                    diagnostics.push(Error::from_internal_at(
+                        ErrorInternal::UnexpectedToken(other),
+                        next_at,
+                    ));
+                    self.recover_operator_list(diagnostics);
+
+                    break;
+                }
+            };
+
+            if next_operator != operator {
+                diagnostics.push(Error::from_internal_at(
+                    ErrorInternal::MismatchedOperatorList(operator.clone(), next_operator),
+                    next_at,
+                ));
+                self.recover_operator_list(diagnostics);
+
+                break;
+            }
+
+            elements.push(self.parse_value_recovering(diagnostics));
+        }
+
+        Self::elements_to_list(elements)
+    }
+
+    /// Recovery variant of [`Self::parse_implicit_form_list`]; see
+    /// [`parse_implicit_form_list_recovering`] for the public entry point.
+    fn parse_implicit_form_list_recovering(&mut self, diagnostics: &mut Vec<Error>) -> Rc<Value> {
+        let mut lists = vec![];
+        let mut at = Span::point((1, 1));
+
+        loop {
+            match self.input.peek() {
+                OkAt(_, next_at) => at = *next_at,
+                ErrAt(e, err_at) => {
+                    diagnostics.push(Error::from_internal_at(e.clone().into(), *err_at));
+                    self.input.next();
+
+                    continue;
+                }
+                NoneAt(_) => break,
+            }
+
+            let list = self.parse_list_recovering(
+                |t| matches!(t, Token::Semicolon | Token::Newline),
+                true,
+                diagnostics,
+            );
+
+            self.input
+                .items_while_successful_if(|t| *t == Token::Semicolon || *t == Token::Newline)
+                .for_each(drop);
+
+            if *list != Value::Nil {
+                self.positions.insert(&list, at);
+                lists.push(list);
+            }
+        }
+
+        Self::elements_to_list(lists)
+    }
 }
 
 pub fn parse_value<I>(input: I) -> Result<(Rc<Value>, PositionMap)>
@@ -336,6 +698,26 @@ where
     result_from_result_at(parser.parse_implicit_form_list()).map(|v| (v, parser.positions))
 }
 
+/// Like [`parse_implicit_form_list`], but never bails on the first error. Borrows
+/// rust-analyzer's recovery-set technique: on an unexpected or erroring token, a diagnostic is
+/// recorded, a [`Value::Error`] placeholder takes its place, and the parser bumps forward to the
+/// next list terminator, `;`, newline, or EOF before resuming. This gives editor tooling every
+/// diagnostic in the input at once, plus a usable partial tree, instead of stopping at the first
+/// stray token.
+pub fn parse_implicit_form_list_recovering<I>(input: I) -> (Rc<Value>, PositionMap, Vec<Error>)
+where
+    I: IntoIterator<Item = char>,
+{
+    let mut parser = Parser {
+        input: tokenize(input.into_iter()),
+        positions: value::ContextMap::new(),
+    };
+    let mut diagnostics = vec![];
+    let value = parser.parse_implicit_form_list_recovering(&mut diagnostics);
+
+    (value, parser.positions, diagnostics)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -346,12 +728,12 @@ mod tests {
     }
 
     fn try_into_display_positions<'a>(
-        parser: impl FnOnce(std::str::Chars<'a>) -> Result<(Rc<Value>, PositionMap)>,
+        parser: impl FnOnce(core::str::Chars<'a>) -> Result<(Rc<Value>, PositionMap)>,
         input: &'a str,
     ) -> Result<String> {
         parser(input.chars()).map(|(_, p)| {
             let mut entries: Vec<_> =
-                unsafe { p.iter().map(|(k, p)| (p, format!("{}", k))).collect() };
+                p.iter().map(|(k, p)| (p.start, format!("{}", k))).collect();
 
             entries.sort();
 
@@ -390,6 +772,35 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn float_values() -> Result<()> {
+        snapshot!(try_parse_display("1.5")?, "1.5");
+        snapshot!(try_parse_display("6.022e23")?, "602200000000000000000000");
+        snapshot!(try_parse_display("1e-9")?, "0.000000001");
+        snapshot!(try_parse_display("3.")?, "3");
+
+        Ok(())
+    }
+
+    #[test]
+    fn char_values() -> Result<()> {
+        snapshot!(try_parse_display(r"'a'")?, r"'a'");
+        snapshot!(try_parse_display(r"'\n'")?, r"'\n'");
+
+        Ok(())
+    }
+
+    #[test]
+    fn raw_string_values() -> Result<()> {
+        snapshot!(try_parse_display(r#"r"a\nb""#)?, r#""a\\nb""#);
+        snapshot!(
+            try_parse_display(r##"r#"embedded "quotes""#"##)?,
+            r#""embedded \"quotes\"""#
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn quoted_values() -> Result<()> {
         snapshot!(try_parse_display("'abc")?, r"'abc");
@@ -506,6 +917,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn char_and_raw_string_save_positions() -> Result<()> {
+        snapshot!(
+            try_into_display_positions(parse_value, "(a 'b' r\"c\")")?,
+            r#"
+{
+    (1, 1): (a 'b' "c"),
+    (1, 2): a,
+    (1, 4): 'b',
+    (1, 8): "c"
+}
+"#
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn single_value_saves_position() -> Result<()> {
         snapshot!(
@@ -578,6 +1006,28 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn recovering_parse_keeps_going_past_errors() {
+        let (value, _, diagnostics) =
+            parse_implicit_form_list_recovering("[1 + 2 * 3] (a".chars());
+
+        snapshot!(format!("{}", value), "(((+ 1 2) (a)))");
+
+        let messages: Vec<_> = diagnostics.iter().map(|e| format!("{}", e)).collect();
+        snapshot!(
+            messages.join("\n"),
+            "mismatched operator list; operator * does not match initial operator + (at line 1, column 8)\nunterminated list (at line 1, column 15)"
+        );
+    }
+
+    #[test]
+    fn recovering_parse_matches_normal_parse_when_input_is_valid() {
+        let (value, _, diagnostics) = parse_implicit_form_list_recovering("a b\ndef d".chars());
+
+        assert_eq!(diagnostics, vec![]);
+        snapshot!(format!("{}", value), r#"((a b) (def d))"#);
+    }
+
     #[test]
     fn implicit_form_list_saves_positions() -> Result<()> {
         snapshot!(
@@ -596,4 +1046,50 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn values_streams_one_top_level_value_per_call() -> Result<()> {
+        let mut parser = Parser::new("a   b\n(c d)".chars());
+
+        let values: Vec<_> = parser
+            .values()
+            .collect::<Result<Vec<_>>>()?
+            .iter()
+            .map(|v| format!("{}", v))
+            .collect();
+
+        snapshot!(values.join(" "), "a b (c d)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn values_stops_cleanly_at_eof() {
+        let mut parser = Parser::new("a".chars());
+
+        assert!(parser.values().next().unwrap().is_ok());
+        assert!(parser.values().next().is_none());
+    }
+
+    #[test]
+    fn at_value_start_reports_whether_a_value_could_start_here() {
+        assert!(!Parser::new(")".chars()).at_value_start());
+        assert!(Parser::new("123".chars()).at_value_start());
+        assert!(!Parser::new("".chars()).at_value_start());
+    }
+
+    #[test]
+    fn values_restarts_positions_per_item() -> Result<()> {
+        let mut parser = Parser::new("a\n  b\n    (c d)".chars());
+        let values = parser.values().collect::<Result<Vec<_>>>()?;
+
+        let positions: Vec<_> = values
+            .iter()
+            .map(|v| format!("{:?}", parser.positions()[v].start))
+            .collect();
+
+        snapshot!(positions.join(", "), "(1, 1), (2, 3), (3, 5)");
+
+        Ok(())
+    }
 }
@@ -4,9 +4,15 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
 use thiserror::Error;
 
-use result_at::{CharReaderError, CharSource, Reader, ResultAt::*, Source};
+use result_at::{CharReaderError, CharSource, Reader, ResultAt::*, Source, Span};
 
 #[derive(Clone, Error, Debug, Eq, PartialEq)]
 pub enum Error {
@@ -17,10 +23,27 @@ pub enum Error {
     #[error("unparsable integer")]
     UnparsableInteger {
         #[from]
-        source: std::num::ParseIntError,
+        source: core::num::ParseIntError,
+    },
+    #[error("unparsable float")]
+    UnparsableFloat {
+        #[from]
+        source: core::num::ParseFloatError,
     },
     #[error("unterminated string")]
     UnterminatedString,
+    #[error("unterminated character literal")]
+    UnterminatedChar,
+    #[error("invalid escape sequence: \\{0}")]
+    InvalidEscape(char),
+    #[error("invalid unicode escape")]
+    InvalidUnicodeEscape,
+    #[error("invalid byte escape")]
+    InvalidByteEscape,
+    #[error("unterminated comment")]
+    UnterminatedComment,
+    #[error("misplaced digit separator")]
+    MisplacedDigitSeparator,
     #[error("EOF")]
     Eof {
         #[from]
@@ -34,10 +57,27 @@ impl From<&CharReaderError> for Error {
     }
 }
 
+impl Error {
+    /// True for errors that only arise because the input ran out partway through a string,
+    /// number prefix, or block comment, as opposed to a genuine syntax error; a
+    /// [`PartialTokenizer`] treats these as [`Partial::Incomplete`] rather than failing outright,
+    /// since more input may still resolve them.
+    fn is_incomplete(&self) -> bool {
+        matches!(
+            self,
+            Error::UnterminatedString
+                | Error::UnterminatedChar
+                | Error::UnterminatedComment
+                | Error::InvalidInteger
+                | Error::Eof { .. }
+        )
+    }
+}
+
 pub type ResultAt<T> = result_at::ResultAt<T, Error>;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum Token {
+#[derive(Clone, Debug)]
+pub enum Token<'a> {
     LParen,
     RParen,
     LBracket,
@@ -47,59 +87,166 @@ pub enum Token {
     Quote,
     Newline,
     Comma,
+    Semicolon,
     Integer(isize),
-    String(String),
-    Identifier(String),
+    Float(f64),
+    Char(char),
+    // Borrowed directly from the source buffer when a `tokenize_str` tokenizer can slice it out
+    // unchanged; only escapes (for `String`) or a non-`&str` source (e.g. a streaming REPL) force
+    // an owned copy.
+    String(Cow<'a, str>),
+    Identifier(Cow<'a, str>),
+}
+
+// Hand-rolled so `Float`'s `f64` can compare by bit pattern, same as `value::OrderedFloat`; `Eq`
+// is sound here since tokens never carry a `NaN` produced by anything but a literal parse.
+impl<'a> PartialEq for Token<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Token::LParen, Token::LParen) => true,
+            (Token::RParen, Token::RParen) => true,
+            (Token::LBracket, Token::LBracket) => true,
+            (Token::RBracket, Token::RBracket) => true,
+            (Token::LBrace, Token::LBrace) => true,
+            (Token::RBrace, Token::RBrace) => true,
+            (Token::Quote, Token::Quote) => true,
+            (Token::Newline, Token::Newline) => true,
+            (Token::Comma, Token::Comma) => true,
+            (Token::Semicolon, Token::Semicolon) => true,
+            (Token::Integer(a), Token::Integer(b)) => a == b,
+            (Token::Float(a), Token::Float(b)) => a.to_bits() == b.to_bits(),
+            (Token::Char(a), Token::Char(b)) => a == b,
+            (Token::String(a), Token::String(b)) => a == b,
+            (Token::Identifier(a), Token::Identifier(b)) => a == b,
+            _ => false,
+        }
+    }
 }
 
+impl<'a> Eq for Token<'a> {}
+
 fn char_is_token_end(c: char) -> bool {
     match c {
-        '(' | ')' | '[' | ']' | '{' | '}' | '\'' | '"' | '\n' | ',' => true,
+        '(' | ')' | '[' | ']' | '{' | '}' | '\'' | '"' | '\n' | ',' | ';' => true,
         _ if c.is_ascii_whitespace() => true,
         _ => false,
     }
 }
 
-pub struct Tokenizer<I: Iterator<Item = char>> {
+/// How a [`Tokenizer`] behaves after yielding an [`Error`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorHandling {
+    /// Treat the stream as dead: every subsequent [`Tokenizer::next`] reports a clean end of
+    /// input instead of reading any further, same as tokenizing has always behaved.
+    Stop,
+    /// Skip ahead to the next safe resynchronization point (the next whitespace, newline, or
+    /// delimiter) and keep tokenizing, so a caller can collect more than one diagnostic from a
+    /// single source in one pass instead of stopping at the first error.
+    Continue,
+}
+
+pub struct Tokenizer<'a, I: Iterator<Item = char>> {
     input: Reader<CharSource<I>>,
     stopped: bool,
+    error_handling: ErrorHandling,
+    // A single character of pushback, used to disambiguate `'a'` (a char literal) from `'abc` (a
+    // quoted value): by the time we know which it is, we may have already consumed the char
+    // following the quote.
+    pending: Option<(char, Span)>,
+    // The original buffer, for `tokenize_str` tokenizers: lets `String`/`Identifier` borrow
+    // straight out of it instead of rebuilding a fresh `String`. `None` for the general
+    // char-iterator case (e.g. a streaming REPL), which has no buffer to borrow from.
+    source_text: Option<&'a str>,
 }
 
-impl<I> Tokenizer<I>
+impl<'a, I> Tokenizer<'a, I>
 where
     I: Iterator<Item = char>,
 {
-    fn tokenize_string(&mut self, at: (usize, usize)) -> ResultAt<Token> {
-        let result = self
-            .input
-            .items_while_successful_if(|x| *x != '\"')
-            .collect();
+    fn tokenize_string(&mut self, at: Span) -> ResultAt<Token<'a>> {
+        let start = self.input.byte_pos();
+        // Without a backing buffer to slice from, we always need to build an owned copy; with
+        // one, we only do so once an escape forces a transformation, borrowing up to that point.
+        let mut owned = if self.source_text.is_some() {
+            None
+        } else {
+            Some(String::new())
+        };
 
-        self.input.next().none_as_err(Error::UnterminatedString)?;
+        loop {
+            let before = self.input.byte_pos();
+            let (c, c_at) = self.input.next().none_as_err(Error::UnterminatedString)?;
 
-        OkAt(Token::String(result), at)
-    }
+            match c {
+                '"' => break,
+                '\\' => {
+                    let buf = owned.get_or_insert_with(|| {
+                        self.source_text.unwrap()[start..before].to_string()
+                    });
+                    buf.push(self.read_escape(c_at, Error::UnterminatedString)?.0);
+                }
+                _ => {
+                    if let Some(buf) = owned.as_mut() {
+                        buf.push(c);
+                    }
+                }
+            }
+        }
+
+        let text = match owned {
+            Some(s) => Cow::Owned(s),
+            None => Cow::Borrowed(&self.source_text.unwrap()[start..self.input.byte_pos() - 1]),
+        };
 
-    fn tokenize_identifier(&mut self, first_char: char, at: (usize, usize)) -> ResultAt<Token> {
         OkAt(
-            Token::Identifier(
-                std::iter::once(first_char)
+            Token::String(text),
+            Span {
+                start: at.start,
+                end: self.input.position(),
+            },
+        )
+    }
+
+    fn tokenize_identifier(&mut self, first_char: char, at: Span) -> ResultAt<Token<'a>> {
+        let start = self.input.byte_pos() - first_char.len_utf8();
+
+        let text = match self.source_text {
+            Some(s) => {
+                self.input
+                    .items_while_successful_if(|x| {
+                        !char_is_token_end(*x) && !x.is_ascii_whitespace()
+                    })
+                    .for_each(drop);
+
+                Cow::Borrowed(&s[start..self.input.byte_pos()])
+            }
+            None => Cow::Owned(
+                core::iter::once(first_char)
                     .chain(self.input.items_while_successful_if(|x| {
                         !char_is_token_end(*x) && !x.is_ascii_whitespace()
                     }))
                     .collect(),
             ),
-            at,
+        };
+
+        OkAt(
+            Token::Identifier(text),
+            Span {
+                start: at.start,
+                end: self.input.position(),
+            },
         )
     }
 
-    fn tokenize_integer(&mut self, mut first_char: char, at: (usize, usize)) -> ResultAt<Token> {
-        let sign = if first_char == '-' {
-            first_char = self.input.next().unwrap().0;
-            -1
-        } else {
-            1
-        };
+    fn tokenize_number(&mut self, mut first_char: char, at: Span) -> ResultAt<Token<'a>> {
+        let negative = first_char == '-';
+        let mut first_at = at;
+
+        if negative {
+            let (c, c_at) = self.input.next().unwrap();
+            first_char = c;
+            first_at = c_at;
+        }
 
         let mut base = 10;
 
@@ -108,94 +255,517 @@ where
                 ('b', _) => {
                     base = 2;
                     self.input.next().none_as_err(Error::InvalidInteger)?;
-                    first_char = self.input.next().none_as_err(Error::InvalidInteger)?.0;
+                    let (c, c_at) = self.input.next().none_as_err(Error::InvalidInteger)?;
+                    first_char = c;
+                    first_at = c_at;
+                }
+                ('o', _) => {
+                    base = 8;
+                    self.input.next().none_as_err(Error::InvalidInteger)?;
+                    let (c, c_at) = self.input.next().none_as_err(Error::InvalidInteger)?;
+                    first_char = c;
+                    first_at = c_at;
                 }
                 ('x', _) => {
                     base = 16;
                     self.input.next().none_as_err(Error::InvalidInteger)?;
-                    first_char = self.input.next().none_as_err(Error::InvalidInteger)?.0;
+                    let (c, c_at) = self.input.next().none_as_err(Error::InvalidInteger)?;
+                    first_char = c;
+                    first_at = c_at;
                 }
                 _ => {}
             }
         }
 
-        let s = std::iter::once(first_char)
-            .chain(
-                self.input
-                    .items_while_successful_if(|x| !char_is_token_end(*x)),
-            )
-            .collect::<String>();
+        let mut digits = vec![(first_char, first_at)];
+
+        loop {
+            match self.input.peek() {
+                OkAt(c, _) if !char_is_token_end(*c) => {
+                    digits.push(self.input.next().unwrap_or_else(|| unreachable!()));
+                }
+                _ => break,
+            }
+        }
+
+        // `_` is only ever a visual group separator (`1_000_000`, `0xDEAD_BEEF`), never a digit
+        // itself, so one at either end of the run or doubled up can't be stripped unambiguously.
+        if digits.first().unwrap().0 == '_' {
+            return ErrAt(Error::MisplacedDigitSeparator, digits[0].1);
+        }
+        if digits.last().unwrap().0 == '_' {
+            return ErrAt(Error::MisplacedDigitSeparator, digits.last().unwrap().1);
+        }
+        for pair in digits.windows(2) {
+            if pair[0].0 == '_' && pair[1].0 == '_' {
+                return ErrAt(Error::MisplacedDigitSeparator, pair[1].1);
+            }
+        }
+
+        let s: String = digits
+            .iter()
+            .filter(|(c, _)| *c != '_')
+            .map(|(c, _)| c)
+            .collect();
+
+        // Only base-10 runs can be floats; a `.` or `e`/`E` in a `0b`/`0x`/`0o` run is just part
+        // of the digit run (a hex digit for `e`/`E`, an invalid digit otherwise), never a float
+        // marker.
+        let at = Span {
+            start: at.start,
+            end: self.input.position(),
+        };
+
+        if base == 10 && (s.contains('.') || s.contains('e') || s.contains('E')) {
+            let signed = if negative { format!("-{}", s) } else { s };
+
+            return ResultAt::from_result(signed.parse::<f64>().map(Token::Float), at);
+        }
+
+        let sign = if negative { -1 } else { 1 };
 
         ResultAt::from_result(
             isize::from_str_radix(&s, base).map(|x| Token::Integer(x * sign)),
             at,
         )
     }
+
+    /// Decodes the escape sequence following a `\` already consumed at `at`, reporting any error
+    /// there rather than at the escape selector character itself. `unterminated` is the error to
+    /// report if input runs out mid-escape, which differs between string and char literal
+    /// callers.
+    fn read_escape(&mut self, at: Span, unterminated: Error) -> ResultAt<char> {
+        let (c, _) = self.input.next().none_as_err(unterminated.clone())?;
+        let at = Span {
+            start: at.start,
+            end: self.input.position(),
+        };
+
+        match c {
+            'n' => OkAt('\n', at),
+            't' => OkAt('\t', at),
+            'r' => OkAt('\r', at),
+            '0' => OkAt('\0', at),
+            '\\' => OkAt('\\', at),
+            '\'' => OkAt('\'', at),
+            '"' => OkAt('"', at),
+            'u' => {
+                expect_char(&mut self.input, '{')?;
+
+                let digits: String = self
+                    .input
+                    .items_while_successful_if(|x| *x != '}')
+                    .collect();
+                self.input.next().none_as_err(unterminated)?;
+                let at = Span {
+                    start: at.start,
+                    end: self.input.position(),
+                };
+
+                if digits.is_empty() || digits.len() > 6 {
+                    return ErrAt(Error::InvalidUnicodeEscape, at);
+                }
+
+                let code_point = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32);
+
+                match code_point {
+                    Some(c) => OkAt(c, at),
+                    None => ErrAt(Error::InvalidUnicodeEscape, at),
+                }
+            }
+            'x' => {
+                let (hi, _) = self.input.next().none_as_err(unterminated.clone())?;
+                let (lo, _) = self.input.next().none_as_err(unterminated)?;
+                let at = Span {
+                    start: at.start,
+                    end: self.input.position(),
+                };
+
+                let value = hi.to_digit(16).zip(lo.to_digit(16)).map(|(hi, lo)| hi * 16 + lo);
+
+                match value.filter(|v| *v <= 0x7F).and_then(char::from_u32) {
+                    Some(c) => OkAt(c, at),
+                    None => ErrAt(Error::InvalidByteEscape, at),
+                }
+            }
+            _ => ErrAt(Error::InvalidEscape(c), at),
+        }
+    }
+
+    /// Disambiguates a char literal (`'a'`, `'\n'`) from a quoted value (`'abc`, `'(1 2)`) by
+    /// peeking at the character following the opening quote: a char literal never contains
+    /// whitespace, so anything else falls through to the normal `Quote` token, with the peeked
+    /// character replayed as the start of the next token.
+    fn tokenize_quote_or_char(&mut self, at: Span) -> ResultAt<Token<'a>> {
+        let is_candidate = matches!(self.input.peek().as_ref(), OkAt(c, _) if !c.is_ascii_whitespace());
+
+        if !is_candidate {
+            return OkAt(Token::Quote, at);
+        }
+
+        let (first, first_at) = self.input.next().unwrap_or_else(|| unreachable!());
+
+        if first == '\\' {
+            let (value, _) = self.read_escape(first_at, Error::UnterminatedChar)?;
+            expect_char(&mut self.input, '\'')?;
+
+            return OkAt(
+                Token::Char(value),
+                Span {
+                    start: at.start,
+                    end: self.input.position(),
+                },
+            );
+        }
+
+        match self.input.peek().as_ref() {
+            OkAt('\'', _) => {
+                self.input.next().unwrap_or_else(|| unreachable!());
+
+                OkAt(
+                    Token::Char(first),
+                    Span {
+                        start: at.start,
+                        end: self.input.position(),
+                    },
+                )
+            }
+            // True end-of-input is a genuine unterminated character literal, not a fallback to
+            // `'` as a lone quote — there's no following character left to reinterpret as one.
+            NoneAt(_) => ErrAt(Error::UnterminatedChar, first_at),
+            _ => {
+                self.pending = Some((first, first_at));
+
+                OkAt(Token::Quote, at)
+            }
+        }
+    }
+
+    /// Tokenizes `r"..."` and `r#"..."#`-style raw strings, with the number of `#`s before the
+    /// opening quote setting the balanced delimiter the closing quote must match.
+    fn tokenize_raw_string(&mut self, at: Span) -> ResultAt<Token<'a>> {
+        let mut hashes = 0;
+        while let OkAt('#', _) = self.input.peek().as_ref() {
+            self.input.next().unwrap_or_else(|| unreachable!());
+            hashes += 1;
+        }
+
+        expect_char(&mut self.input, '"')?;
+
+        let mut content = String::new();
+
+        loop {
+            let (c, _) = self.input.next().none_as_err(Error::UnterminatedString)?;
+
+            if c != '"' {
+                content.push(c);
+                continue;
+            }
+
+            let mut matched_hashes = 0;
+            while matched_hashes < hashes {
+                match self.input.peek().as_ref() {
+                    OkAt('#', _) => {
+                        self.input.next().unwrap_or_else(|| unreachable!());
+                        matched_hashes += 1;
+                    }
+                    _ => break,
+                }
+            }
+
+            if matched_hashes == hashes {
+                return OkAt(
+                    Token::String(Cow::Owned(content)),
+                    Span {
+                        start: at.start,
+                        end: self.input.position(),
+                    },
+                );
+            }
+
+            content.push('"');
+            content.extend(core::iter::repeat('#').take(matched_hashes));
+        }
+    }
+}
+
+fn expect_char<S: Source<Output = char, Error = CharReaderError>>(
+    input: &mut Reader<S>,
+    expected: char,
+) -> ResultAt<()> {
+    match input.next() {
+        OkAt(c, at) if c == expected => OkAt((), at),
+        OkAt(c, at) => ErrAt(Error::UnexpectedChar(c), at),
+        ErrAt(e, at) => ErrAt(e.into(), at),
+        NoneAt(at) => ErrAt(
+            Error::Eof {
+                source: CharReaderError::Eof,
+            },
+            at,
+        ),
+    }
 }
 
-impl<I> Source for Tokenizer<I>
+impl<'a, I> Source for Tokenizer<'a, I>
 where
     I: Iterator<Item = char>,
 {
-    type Output = Token;
+    type Output = Token<'a>;
     type Error = Error;
 
-    fn next(&mut self) -> ResultAt<Token> {
-        use Token::*;
+    fn next(&mut self) -> ResultAt<Token<'a>> {
+        if self.stopped {
+            return NoneAt(Span::point(self.input.position()));
+        }
 
         self.input
             .items_while_successful_if(|x| x.is_ascii_whitespace() && *x != '\n')
             .for_each(drop);
 
-        let result = self
-            .input
-            .next()
-            .map_err(Error::from)
-            .and_then_at(|c, at| match c {
-                '(' => OkAt(LParen, at),
-                ')' => OkAt(RParen, at),
-                '[' => OkAt(LBracket, at),
-                ']' => OkAt(RBracket, at),
-                '{' => OkAt(LBrace, at),
-                '}' => OkAt(RBrace, at),
-                '\'' => OkAt(Quote, at),
-                '\n' => OkAt(Newline, at),
-                ',' => OkAt(Comma, at),
-                '"' => self.tokenize_string(at),
-                _ if c.is_ascii_digit() => self.tokenize_integer(c, at),
-                '-' if self.input.peek().map_or(false, |c2| c2.is_ascii_digit()) => {
-                    self.tokenize_integer(c, at)
-                }
-                _ if !c.is_control() => self.tokenize_identifier(c, at),
-                _ => ErrAt(Error::UnexpectedChar(c), at),
-            });
+        let before = self.input.byte_pos();
+
+        let result = if let Some((c, at)) = self.pending.take() {
+            self.dispatch(c, at)
+        } else {
+            self.input
+                .next()
+                .map_err(Error::from)
+                .and_then_at(|c, at| self.dispatch(c, at))
+        };
 
         if let ErrAt(_, _) = result {
-            self.stopped = true;
+            match self.error_handling {
+                ErrorHandling::Stop => self.stopped = true,
+                ErrorHandling::Continue => self.resync(self.input.byte_pos() > before),
+            }
         }
 
         result
     }
 }
 
-pub fn tokenize<I>(input: I) -> Reader<Tokenizer<I::IntoIter>>
+impl<'a, I> Tokenizer<'a, I>
+where
+    I: Iterator<Item = char>,
+{
+    /// Consumes a `#| ... |#`-style block comment, with the opening `#|` already consumed.
+    /// Nested `#|`s are tracked so that a comment can contain another comment without being
+    /// closed by its inner `|#`.
+    fn skip_block_comment(&mut self, at: Span) -> ResultAt<()> {
+        let mut depth = 1;
+
+        loop {
+            let (c, _) = self.input.next().none_as_err(Error::UnterminatedComment)?;
+
+            match (c, self.input.peek().as_ref()) {
+                ('#', OkAt('|', _)) => {
+                    self.input.next().unwrap_or_else(|| unreachable!());
+                    depth += 1;
+                }
+                ('|', OkAt('#', _)) => {
+                    self.input.next().unwrap_or_else(|| unreachable!());
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return OkAt(
+                            (),
+                            Span {
+                                start: at.start,
+                                end: self.input.position(),
+                            },
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// After an error in [`ErrorHandling::Continue`] mode, skips ahead to the next whitespace,
+    /// newline, or delimiter so the next call to `next()` resumes at a safe boundary instead of
+    /// re-tripping over the same input. `consumed_for_error` is whether producing the error
+    /// itself already consumed at least one character (true in every normal case); when it
+    /// didn't, one character is force-consumed first to guarantee progress and avoid spinning
+    /// forever on the same offending byte.
+    fn resync(&mut self, consumed_for_error: bool) {
+        if !consumed_for_error {
+            self.input.next();
+        }
+
+        self.input
+            .items_while_successful_if(|c| !char_is_token_end(*c))
+            .for_each(drop);
+    }
+
+    fn dispatch(&mut self, c: char, at: Span) -> ResultAt<Token<'a>> {
+        use Token::*;
+
+        match c {
+            '(' => OkAt(LParen, at),
+            ')' => OkAt(RParen, at),
+            '[' => OkAt(LBracket, at),
+            ']' => OkAt(RBracket, at),
+            '{' => OkAt(LBrace, at),
+            '}' => OkAt(RBrace, at),
+            '\'' => self.tokenize_quote_or_char(at),
+            '\n' => OkAt(Newline, at),
+            ',' => OkAt(Comma, at),
+            // A single `;` is the statement separator, so a comment needs a distinct marker;
+            // doubling it up (`;;`) is the natural choice and keeps `;` available on its own.
+            ';' if matches!(self.input.peek().as_ref(), OkAt(';', _)) => {
+                self.input.next().unwrap_or_else(|| unreachable!());
+                self.input
+                    .items_while_successful_if(|x| *x != '\n')
+                    .for_each(drop);
+
+                self.next()
+            }
+            ';' => OkAt(Semicolon, at),
+            '#' if matches!(self.input.peek().as_ref(), OkAt('|', _)) => {
+                self.input.next().unwrap_or_else(|| unreachable!());
+                self.skip_block_comment(at)?;
+
+                self.next()
+            }
+            '"' => self.tokenize_string(at),
+            'r' if matches!(self.input.peek().as_ref(), OkAt('"', _) | OkAt('#', _)) => {
+                self.tokenize_raw_string(at)
+            }
+            _ if c.is_ascii_digit() => self.tokenize_number(c, at),
+            '-' if self.input.peek().map_or(false, |c2| c2.is_ascii_digit()) => {
+                self.tokenize_number(c, at)
+            }
+            // A leading `.` (`.5`) is a float, not the start of an identifier; `.` with nothing
+            // or a non-digit after it isn't a number at all, so it falls through below.
+            '.' if self.input.peek().map_or(false, |c2| c2.is_ascii_digit()) => {
+                self.tokenize_number(c, at)
+            }
+            _ if !c.is_control() => self.tokenize_identifier(c, at),
+            _ => ErrAt(Error::UnexpectedChar(c), at),
+        }
+    }
+}
+
+/// Tokenizes an arbitrary char source (e.g. a streaming REPL) with no backing buffer to borrow
+/// from, so `Token::String`/`Token::Identifier` always hold an owned copy.
+pub fn tokenize<I>(input: I) -> Reader<Tokenizer<'static, I::IntoIter>>
+where
+    I: IntoIterator<Item = char>,
+{
+    tokenize_with_error_handling(input, ErrorHandling::Stop)
+}
+
+/// Like [`tokenize`], but with an explicit [`ErrorHandling`] mode, so a caller that wants every
+/// lex error in a source rather than just the first can pass [`ErrorHandling::Continue`].
+pub fn tokenize_with_error_handling<I>(
+    input: I,
+    error_handling: ErrorHandling,
+) -> Reader<Tokenizer<'static, I::IntoIter>>
 where
     I: IntoIterator<Item = char>,
 {
     Tokenizer {
         input: CharSource::new(input.into_iter()).reader(),
         stopped: false,
+        error_handling,
+        pending: None,
+        source_text: None,
+    }
+    .reader()
+}
+
+/// Tokenizes a whole in-memory `&str`, letting unescaped `Token::String`/`Token::Identifier`
+/// values borrow straight out of `input` instead of allocating a fresh `String` each time.
+pub fn tokenize_str(input: &str) -> Reader<Tokenizer<'_, core::str::Chars<'_>>> {
+    tokenize_str_with_error_handling(input, ErrorHandling::Stop)
+}
+
+/// Like [`tokenize_str`], but with an explicit [`ErrorHandling`] mode, so a caller that wants
+/// every lex error in a source rather than just the first can pass [`ErrorHandling::Continue`].
+pub fn tokenize_str_with_error_handling(
+    input: &str,
+    error_handling: ErrorHandling,
+) -> Reader<Tokenizer<'_, core::str::Chars<'_>>> {
+    Tokenizer {
+        input: CharSource::new(input.chars()).reader(),
+        stopped: false,
+        error_handling,
+        pending: None,
+        source_text: Some(input),
     }
     .reader()
 }
 
+/// The result of asking a [`PartialTokenizer`] for its next token.
+#[derive(Debug)]
+pub enum Partial<T> {
+    /// A token, a genuine syntax error, or clean end-of-input: the usual outcome of tokenizing,
+    /// just as [`tokenize`] would report it.
+    Done(T),
+    /// The input fed so far ends partway through a string, number prefix, or block comment; call
+    /// [`PartialTokenizer::feed`] with more input and ask again.
+    Incomplete,
+}
+
+/// A resumable tokenizer for input that may arrive in pieces (e.g. a REPL reading line by line,
+/// or an editor buffer being typed into). Unlike [`tokenize`], which treats running out of input
+/// mid-token as a hard error, `PartialTokenizer` reports [`Partial::Incomplete`] instead and picks
+/// back up right where it left off once [`Self::feed`] supplies the rest.
+///
+/// Re-tokenizes the unconsumed tail of its buffer on every call rather than threading a paused
+/// state machine through `feed`; simpler, and cheap enough for the REPL/editor-sized inputs this
+/// is meant for.
+pub struct PartialTokenizer {
+    buffer: String,
+    consumed: usize,
+}
+
+impl PartialTokenizer {
+    /// Appends more source text to be tokenized.
+    pub fn feed(&mut self, input: &str) {
+        self.buffer.push_str(input);
+    }
+
+    /// Returns the next token from the input fed so far, or [`Partial::Incomplete`] if it ends
+    /// partway through a string, number prefix, or block comment.
+    pub fn next_token(&mut self) -> Partial<ResultAt<Token<'static>>> {
+        let mut tokenizer = Tokenizer {
+            input: CharSource::new(self.buffer[self.consumed..].chars()).reader(),
+            stopped: false,
+            error_handling: ErrorHandling::Stop,
+            pending: None,
+            source_text: None,
+        };
+
+        match Source::next(&mut tokenizer) {
+            ErrAt(e, _) if e.is_incomplete() => Partial::Incomplete,
+            result => {
+                self.consumed += tokenizer.input.byte_pos();
+
+                Partial::Done(result)
+            }
+        }
+    }
+}
+
+/// Creates a resumable [`PartialTokenizer`] with no input yet; feed it via
+/// [`PartialTokenizer::feed`].
+pub fn tokenize_partial() -> PartialTokenizer {
+    PartialTokenizer {
+        buffer: String::new(),
+        consumed: 0,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use k9::{assert_err_matches_regex, snapshot};
-    type Result<T> = std::result::Result<T, Error>;
+    type Result<T> = core::result::Result<T, Error>;
 
-    fn try_tokenize(input: &str) -> Result<Vec<Token>> {
+    fn try_tokenize(input: &str) -> Result<Vec<Token<'static>>> {
         tokenize(input.chars())
             .iter_results()
             .filter(|r| match r {
@@ -205,18 +775,46 @@ mod tests {
             .collect()
     }
 
-    fn try_tokenize_uncollapsed(input: &str) -> Vec<Result<Token>> {
+    fn try_tokenize_uncollapsed(input: &str) -> Vec<Result<Token<'static>>> {
         tokenize(input.chars()).iter_results().collect()
     }
 
-    fn try_tokenize_full(input: &str) -> Vec<ResultAt<Token>> {
+    fn try_tokenize_full(input: &str) -> Vec<ResultAt<Token<'static>>> {
         tokenize(input.chars()).iter().collect()
     }
 
+    // `Reader::iter`/`iter_results` stop at the first terminal result regardless of what the
+    // underlying `Tokenizer` does, so collecting every diagnostic in `Continue` mode means
+    // driving `Reader::next` by hand until a clean `NoneAt` is actually reached.
+    fn try_tokenize_continuing(input: &str) -> Vec<Result<Token<'static>>> {
+        let mut reader = tokenize_with_error_handling(input.chars(), ErrorHandling::Continue);
+        let mut results = Vec::new();
+
+        loop {
+            match reader.next() {
+                OkAt(token, _) => results.push(Ok(token)),
+                ErrAt(e, _) => results.push(Err(e)),
+                NoneAt(_) => break,
+            }
+        }
+
+        results
+    }
+
+    fn try_tokenize_str(input: &str) -> Result<Vec<Token<'_>>> {
+        tokenize_str(input)
+            .iter_results()
+            .filter(|r| match r {
+                Err(Error::Eof { .. }) => false,
+                _ => true,
+            })
+            .collect()
+    }
+
     #[test]
     fn single_character_tokens() -> Result<()> {
         snapshot!(
-            try_tokenize("()[]{}',")?,
+            try_tokenize("()[]{}',;")?,
             "
 [
     LParen,
@@ -227,6 +825,7 @@ mod tests {
     RBrace,
     Quote,
     Comma,
+    Semicolon,
 ]
 "
         );
@@ -242,18 +841,25 @@ mod tests {
     }
 
     #[test]
-    fn tokenizing_stops_after_error() -> Result<()> {
+    fn line_comments_are_skipped_up_to_the_newline() -> Result<()> {
         snapshot!(
-            try_tokenize_uncollapsed("(\x07)"),
-            r"
+            try_tokenize(";; a comment\n123 ;; trailing\n; not a comment")?,
+            "
 [
-    Ok(
-        LParen,
+    Newline,
+    Integer(
+        123,
     ),
-    Err(
-        UnexpectedChar(
-            '\u{7}',
-        ),
+    Newline,
+    Semicolon,
+    Identifier(
+        \"not\",
+    ),
+    Identifier(
+        \"a\",
+    ),
+    Identifier(
+        \"comment\",
     ),
 ]
 "
@@ -263,64 +869,209 @@ mod tests {
     }
 
     #[test]
-    fn basic_strings() -> Result<()> {
+    fn block_comments_can_nest() -> Result<()> {
         snapshot!(
-            try_tokenize(r#""""a""abc""#)?,
-            r#"
+            try_tokenize("1 #| a #| nested |# comment |# 2")?,
+            "
 [
-    String(
-        "",
-    ),
-    String(
-        "a",
+    Integer(
+        1,
     ),
-    String(
-        "abc",
+    Integer(
+        2,
     ),
 ]
-"#
+"
         );
 
         Ok(())
     }
 
     #[test]
-    fn unterminated_string() -> Result<()> {
-        assert_err_matches_regex!(try_tokenize("\"abc"), r#"Unterminated"#);
+    fn unterminated_block_comment() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize("#| never closed"), "UnterminatedComment");
+        assert_err_matches_regex!(try_tokenize("#| outer #| inner |#"), "UnterminatedComment");
 
         Ok(())
     }
 
     #[test]
-    fn space_separated_tokens() -> Result<()> {
+    fn tokenizing_stops_after_error() -> Result<()> {
         snapshot!(
-            try_tokenize("( \"abc\"\t\n{}")?,
-            r#"
+            try_tokenize_uncollapsed("(\x07)"),
+            r"
 [
-    LParen,
-    String(
-        "abc",
+    Ok(
+        LParen,
+    ),
+    Err(
+        UnexpectedChar(
+            '\u{7}',
+        ),
     ),
-    Newline,
-    LBrace,
-    RBrace,
 ]
-"#
+"
         );
 
         Ok(())
     }
 
     #[test]
-    fn identifiers() -> Result<()> {
+    fn continue_mode_resyncs_past_an_error_instead_of_stopping() {
         snapshot!(
-            try_tokenize("identifier1 identifier!2?)identifier3")?,
-            r#"
+            try_tokenize_continuing("(\x07 123 \x07)"),
+            r"
 [
-    Identifier(
-        "identifier1",
+    Ok(
+        LParen,
     ),
-    Identifier(
+    Err(
+        UnexpectedChar(
+            '\u{7}',
+        ),
+    ),
+    Ok(
+        Integer(
+            123,
+        ),
+    ),
+    Err(
+        UnexpectedChar(
+            '\u{7}',
+        ),
+    ),
+    Ok(
+        RParen,
+    ),
+]
+"
+        );
+    }
+
+    #[test]
+    fn continue_mode_collapses_a_run_of_bad_characters_into_one_error_and_resync() {
+        // The resync point is the next delimiter, so a whole run of consecutive bad bytes (none
+        // of which are delimiters themselves) is skipped in one go rather than reported one
+        // error at a time.
+        snapshot!(
+            try_tokenize_continuing("\x07\x07\x07 1"),
+            r"
+[
+    Err(
+        UnexpectedChar(
+            '\u{7}',
+        ),
+    ),
+    Ok(
+        Integer(
+            1,
+        ),
+    ),
+]
+"
+        );
+    }
+
+    #[test]
+    fn basic_strings() -> Result<()> {
+        snapshot!(
+            try_tokenize(r#""""a""abc""#)?,
+            r#"
+[
+    String(
+        "",
+    ),
+    String(
+        "a",
+    ),
+    String(
+        "abc",
+    ),
+]
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_string() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize("\"abc"), r#"Unterminated"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn string_escapes() -> Result<()> {
+        snapshot!(
+            try_tokenize(r#""a\nb\t\r\0\\\"\'c" "\u{1F600}" "\x41\x7f""#)?,
+            "
+[
+    String(
+        \"a\\nb\\t\\r\\0\\\\\\\"'c\",
+    ),
+    String(
+        \"😀\",
+    ),
+    String(
+        \"A\\u{7f}\",
+    ),
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_string_mid_escape() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize(r#""abc\"#), r#"Unterminated"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_string_escapes() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize(r#""\q""#), "InvalidEscape");
+        assert_err_matches_regex!(try_tokenize(r#""\u{}""#), "InvalidUnicodeEscape");
+        assert_err_matches_regex!(try_tokenize(r#""\u{1000000}""#), "InvalidUnicodeEscape");
+        assert_err_matches_regex!(try_tokenize(r#""\u{D800}""#), "InvalidUnicodeEscape");
+        assert_err_matches_regex!(try_tokenize(r#""\x80""#), "InvalidByteEscape");
+        assert_err_matches_regex!(try_tokenize(r#""\xzz""#), "InvalidByteEscape");
+
+        Ok(())
+    }
+
+    #[test]
+    fn space_separated_tokens() -> Result<()> {
+        snapshot!(
+            try_tokenize("( \"abc\"\t\n{}")?,
+            r#"
+[
+    LParen,
+    String(
+        "abc",
+    ),
+    Newline,
+    LBrace,
+    RBrace,
+]
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn identifiers() -> Result<()> {
+        snapshot!(
+            try_tokenize("identifier1 identifier!2?)identifier3")?,
+            r#"
+[
+    Identifier(
+        "identifier1",
+    ),
+    Identifier(
         "identifier!2?",
     ),
     RParen,
@@ -406,6 +1157,134 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn floats() -> Result<()> {
+        snapshot!(
+            try_tokenize_uncollapsed("1.5 6.022e23 1e-9 3. -2.5 .5"),
+            "
+[
+    Ok(
+        Float(
+            1.5,
+        ),
+    ),
+    Ok(
+        Float(
+            6.022e23,
+        ),
+    ),
+    Ok(
+        Float(
+            1e-9,
+        ),
+    ),
+    Ok(
+        Float(
+            3.0,
+        ),
+    ),
+    Ok(
+        Float(
+            -2.5,
+        ),
+    ),
+    Ok(
+        Float(
+            0.5,
+        ),
+    ),
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn char_literals() -> Result<()> {
+        snapshot!(
+            try_tokenize(r"'a' '\n' '\'' '\u{1F600}'")?,
+            r#"
+[
+    Char(
+        'a',
+    ),
+    Char(
+        '\n',
+    ),
+    Char(
+        '\'',
+    ),
+    Char(
+        '😀',
+    ),
+]
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn quote_still_disambiguates_from_char() -> Result<()> {
+        snapshot!(
+            try_tokenize("'abc")?,
+            r#"
+[
+    Quote,
+    Identifier(
+        "abc",
+    ),
+]
+"#
+        );
+
+        snapshot!(
+            try_tokenize("'(1 2)")?,
+            "
+[
+    Quote,
+    LParen,
+    Integer(
+        1,
+    ),
+    Integer(
+        2,
+    ),
+    RParen,
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unterminated_char() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize("'a"), r#"UnterminatedChar"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn raw_strings() -> Result<()> {
+        snapshot!(
+            try_tokenize(r##"r"a\nb" r#"embedded "quotes""#"##)?,
+            r#"
+[
+    String(
+        "a\\nb",
+    ),
+    String(
+        "embedded \"quotes\"",
+    ),
+]
+"#
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn partial_integer() -> Result<()> {
         assert_err_matches_regex!(try_tokenize("0b"), r#"InvalidInteger"#);
@@ -428,6 +1307,194 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn hex_and_binary_literals_never_become_floats() -> Result<()> {
+        // A `.` or `e`/`E` in a `0x`/`0b` run is just another (possibly invalid) digit, never a
+        // float marker, since float detection only ever applies to base-10 runs.
+        assert_err_matches_regex!(try_tokenize("0x1.5"), r#"UnparsableInteger.*Digit"#);
+        assert_err_matches_regex!(try_tokenize("0b1e1"), r#"UnparsableInteger.*Digit"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn octal_literals() -> Result<()> {
+        snapshot!(
+            try_tokenize_uncollapsed("0o17 -0o17"),
+            "
+[
+    Ok(
+        Integer(
+            15,
+        ),
+    ),
+    Ok(
+        Integer(
+            -15,
+        ),
+    ),
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn digit_separators_are_stripped_before_parsing() -> Result<()> {
+        snapshot!(
+            try_tokenize_uncollapsed("1_000_000 0xDEAD_BEEF 0b1010_0101 0o1_7 1_000.5"),
+            "
+[
+    Ok(
+        Integer(
+            1000000,
+        ),
+    ),
+    Ok(
+        Integer(
+            3735928559,
+        ),
+    ),
+    Ok(
+        Integer(
+            165,
+        ),
+    ),
+    Ok(
+        Integer(
+            15,
+        ),
+    ),
+    Ok(
+        Float(
+            1000.5,
+        ),
+    ),
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn misplaced_digit_separators_are_rejected() -> Result<()> {
+        assert_err_matches_regex!(try_tokenize("100_"), r#"MisplacedDigitSeparator"#);
+        assert_err_matches_regex!(try_tokenize("1__00"), r#"MisplacedDigitSeparator"#);
+        assert_err_matches_regex!(try_tokenize("0x_FF"), r#"MisplacedDigitSeparator"#);
+        assert_err_matches_regex!(try_tokenize("0x"), r#"InvalidInteger"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_str_borrows_unescaped_strings_and_identifiers() -> Result<()> {
+        let tokens = try_tokenize_str(r#"identifier "a string""#)?;
+
+        match &tokens[0] {
+            Token::Identifier(Cow::Borrowed(_)) => {}
+            t => panic!("expected borrowed identifier, got {:?}", t),
+        }
+        match &tokens[1] {
+            Token::String(Cow::Borrowed(_)) => {}
+            t => panic!("expected borrowed string, got {:?}", t),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn tokenize_str_owns_escaped_strings() -> Result<()> {
+        let tokens = try_tokenize_str(r#""a\nb""#)?;
+
+        match &tokens[0] {
+            Token::String(Cow::Owned(s)) => assert_eq!(s, "a\nb"),
+            t => panic!("expected owned string, got {:?}", t),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn partial_tokenizer_resolves_a_complete_token_in_one_feed() {
+        let mut tok = tokenize_partial();
+        tok.feed("123");
+
+        assert!(matches!(
+            tok.next_token(),
+            Partial::Done(OkAt(Token::Integer(123), _))
+        ));
+    }
+
+    #[test]
+    fn partial_tokenizer_waits_on_an_unterminated_string() {
+        let mut tok = tokenize_partial();
+        tok.feed("\"abc");
+
+        assert!(matches!(tok.next_token(), Partial::Incomplete));
+
+        tok.feed("def\"");
+
+        match tok.next_token() {
+            Partial::Done(OkAt(Token::String(s), _)) => assert_eq!(s, "abcdef"),
+            other => panic!("expected a completed string token, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn partial_tokenizer_waits_on_a_bare_hex_or_binary_prefix() {
+        let mut tok = tokenize_partial();
+        tok.feed("0x");
+
+        assert!(matches!(tok.next_token(), Partial::Incomplete));
+
+        tok.feed("46aF");
+
+        assert!(matches!(
+            tok.next_token(),
+            Partial::Done(OkAt(Token::Integer(18095), _))
+        ));
+    }
+
+    #[test]
+    fn partial_tokenizer_waits_on_an_unclosed_block_comment() {
+        let mut tok = tokenize_partial();
+        tok.feed("#| still going");
+
+        assert!(matches!(tok.next_token(), Partial::Incomplete));
+
+        tok.feed(" |# 123");
+
+        assert!(matches!(
+            tok.next_token(),
+            Partial::Done(OkAt(Token::Integer(123), _))
+        ));
+    }
+
+    #[test]
+    fn partial_tokenizer_does_not_treat_a_real_syntax_error_as_incomplete() {
+        let mut tok = tokenize_partial();
+        tok.feed("\x07");
+
+        assert!(matches!(
+            tok.next_token(),
+            Partial::Done(ErrAt(Error::UnexpectedChar('\u{7}'), _))
+        ));
+    }
+
+    #[test]
+    fn partial_tokenizer_reports_clean_eof_as_done_not_incomplete() {
+        let mut tok = tokenize_partial();
+        tok.feed("123");
+
+        assert!(matches!(
+            tok.next_token(),
+            Partial::Done(OkAt(Token::Integer(123), _))
+        ));
+        assert!(matches!(tok.next_token(), Partial::Done(NoneAt(_))));
+    }
+
     #[test]
     fn multiline() -> Result<()> {
         snapshot!(
@@ -443,90 +1510,232 @@ mod tests {
         Integer(
             1234,
         ),
-        (
-            1,
-            1,
-        ),
+        Span {
+            start: (
+                1,
+                1,
+            ),
+            end: (
+                1,
+                5,
+            ),
+        },
     ),
     OkAt(
         Newline,
-        (
-            1,
-            5,
-        ),
+        Span {
+            start: (
+                1,
+                5,
+            ),
+            end: (
+                2,
+                1,
+            ),
+        },
     ),
     OkAt(
         LParen,
-        (
-            2,
-            1,
-        ),
+        Span {
+            start: (
+                2,
+                1,
+            ),
+            end: (
+                2,
+                2,
+            ),
+        },
     ),
     OkAt(
         Newline,
-        (
-            2,
-            2,
-        ),
+        Span {
+            start: (
+                2,
+                2,
+            ),
+            end: (
+                3,
+                1,
+            ),
+        },
     ),
     OkAt(
         LParen,
-        (
-            3,
-            2,
-        ),
+        Span {
+            start: (
+                3,
+                2,
+            ),
+            end: (
+                3,
+                3,
+            ),
+        },
     ),
     OkAt(
         Integer(
             456,
         ),
-        (
-            3,
-            4,
-        ),
+        Span {
+            start: (
+                3,
+                4,
+            ),
+            end: (
+                3,
+                7,
+            ),
+        },
     ),
     OkAt(
         RParen,
-        (
-            3,
-            8,
-        ),
+        Span {
+            start: (
+                3,
+                8,
+            ),
+            end: (
+                3,
+                9,
+            ),
+        },
     ),
     OkAt(
         Newline,
-        (
-            3,
-            9,
-        ),
+        Span {
+            start: (
+                3,
+                9,
+            ),
+            end: (
+                4,
+                1,
+            ),
+        },
     ),
     OkAt(
         LBracket,
-        (
-            4,
-            3,
-        ),
+        Span {
+            start: (
+                4,
+                3,
+            ),
+            end: (
+                4,
+                4,
+            ),
+        },
     ),
     OkAt(
         String(
             "abc",
         ),
-        (
-            4,
-            4,
-        ),
+        Span {
+            start: (
+                4,
+                4,
+            ),
+            end: (
+                4,
+                9,
+            ),
+        },
     ),
     OkAt(
         RParen,
-        (
-            4,
-            9,
-        ),
+        Span {
+            start: (
+                4,
+                9,
+            ),
+            end: (
+                4,
+                10,
+            ),
+        },
     ),
     NoneAt(
-        (
-            4,
-            10,
+        Span {
+            start: (
+                4,
+                10,
+            ),
+            end: (
+                4,
+                10,
+            ),
+        },
+    ),
+]
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_spans_cover_the_whole_token() -> Result<()> {
+        snapshot!(
+            try_tokenize_full("identifier 123 \"abc\""),
+            r#"
+[
+    OkAt(
+        Identifier(
+            "identifier",
         ),
+        Span {
+            start: (
+                1,
+                1,
+            ),
+            end: (
+                1,
+                11,
+            ),
+        },
+    ),
+    OkAt(
+        Integer(
+            123,
+        ),
+        Span {
+            start: (
+                1,
+                12,
+            ),
+            end: (
+                1,
+                15,
+            ),
+        },
+    ),
+    OkAt(
+        String(
+            "abc",
+        ),
+        Span {
+            start: (
+                1,
+                16,
+            ),
+            end: (
+                1,
+                21,
+            ),
+        },
+    ),
+    NoneAt(
+        Span {
+            start: (
+                1,
+                21,
+            ),
+            end: (
+                1,
+                21,
+            ),
+        },
     ),
 ]
 "#
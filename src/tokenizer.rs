@@ -16,16 +16,47 @@
  * Foundation, Inc., 51 Franklin St, Fifth Floor, Boston, MA  02110-1301  USA
  */
 
+// This is a standalone, `std`-only tokenizer predating `parser::tokenizer`, the `no_std`,
+// `result_at`-based tokenizer the compiler and VM actually run on. It is not wired into that
+// pipeline and `compiler`/`vm` never call into it; its only caller in this crate is the
+// `scarab-tokenize` debug binary (`src/main.rs`), which dumps its token stream for inspection.
+// Treat it as a separate library tokenizer, not an alternate front-end for `scarab` programs.
+
 //# Tokenizer
 
 //## Imports
+use std::collections::VecDeque;
 use std::error::Error;
 use std::fs::File;
 use std::io;
 use std::io::{Bytes, Cursor, ErrorKind, Read};
 use std::iter::Peekable;
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding;
+use unicode_ident::{is_xid_continue, is_xid_start};
+use unicode_normalization::UnicodeNormalization;
 use unicode_reader::CodePoints;
 
+//### `PromptStyle`, `LexSource`
+//
+// An interactive front-end (a REPL) can supply a `LexSource` so the tokenizer asks for more
+// input instead of failing outright when it runs out of characters partway through a string,
+// backquoted string, or block comment. `PromptStyle` tells the front-end *why* more input is
+// needed, so it can print an appropriate continuation prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptStyle {
+    First,
+    ContinueString,
+    ContinueBackquote,
+    ContinueComment
+}
+
+pub trait LexSource {
+    // Returns more source text, or an empty string if no more is available (at which point the
+    // tokenizer falls back to its usual `IncompleteInput` error).
+    fn read(&mut self, prompt: PromptStyle) -> String;
+}
+
 //## Types
 //
 //### `TokenContents`
@@ -47,10 +78,37 @@ pub enum TokenContents {
     Real(f64),
     Identifier(String),
     Str(String),
+    Comment(String),
     // Should _never_ leave the inner tokenization loop
     NoMatch
 }
 
+// A single point in the source: a byte offset plus the line/col it falls on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub position: usize,
+    pub line: usize,
+    pub col: usize
+}
+
+// A token's full extent: its first position through one past its last, so a parser or error
+// reporter can underline the whole token rather than just its start.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    start: SourceLocation,
+    end: SourceLocation
+}
+
+impl Span {
+    pub fn start(&self) -> SourceLocation {
+        self.start
+    }
+
+    pub fn end(&self) -> SourceLocation {
+        self.end
+    }
+}
+
 //### `Token`
 //
 // A particular token, with contents, a line and column, and byte position within the source.
@@ -59,7 +117,22 @@ pub struct Token {
     contents: TokenContents,
     position: usize,
     line: usize,
-    col: usize
+    col: usize,
+    // The position/line/col of the character just past the end of this token.
+    end: SourceLocation
+}
+
+impl Token {
+    pub fn span(&self) -> Span {
+        Span {
+            start: SourceLocation {
+                position: self.position,
+                line: self.line,
+                col: self.col
+            },
+            end: self.end
+        }
+    }
 }
 
 //### `TokenErrorKind`, `TokenError`
@@ -68,7 +141,38 @@ pub struct Token {
 pub enum TokenErrorKind {
     IO(ErrorKind, String),
     IncompleteInput(String),
-    InvalidChar(char, String)
+    InvalidChar(char, String),
+    InvalidNumber(String)
+}
+
+// Common Unicode homoglyphs mapped to the ASCII character an author most likely meant, so an
+// `InvalidChar` error can suggest a fix instead of just reporting failure. Ordered by code point
+// for ease of scanning; lookup is a linear scan, which costs nothing on the (overwhelmingly
+// common) ASCII path, since this table is only ever consulted once a character has already
+// failed every other tokenization rule.
+const CONFUSABLES: &'static [(char, char)] = &[
+    ('\u{00A0}', ' '),  // no-break space
+    ('\u{2013}', '-'),  // en dash
+    ('\u{2014}', '-'),  // em dash
+    ('\u{2018}', '\''), // left single quotation mark
+    ('\u{2019}', '\''), // right single quotation mark
+    ('\u{201C}', '"'),  // left double quotation mark
+    ('\u{201D}', '"'),  // right double quotation mark
+    ('\u{3000}', ' '),  // ideographic space
+    ('\u{FF08}', '('),  // fullwidth left parenthesis
+    ('\u{FF09}', ')'),  // fullwidth right parenthesis
+    ('\u{FF0C}', ','),  // fullwidth comma
+    ('\u{FF3B}', '['),  // fullwidth left square bracket
+    ('\u{FF3D}', ']'),  // fullwidth right square bracket
+    ('\u{FF5B}', '{'),  // fullwidth left curly bracket
+    ('\u{FF5D}', '}'),  // fullwidth right curly bracket
+];
+
+fn confusable_suggestion(ch: char) -> Option<char> {
+    CONFUSABLES
+        .iter()
+        .find(|(confusable, _)| *confusable == ch)
+        .map(|(_, suggestion)| *suggestion)
 }
 
 #[derive(Debug)]
@@ -87,11 +191,19 @@ pub struct Tokenizer<R: Read> {
     pub filename: String,
 
     reader: Peekable<CodePoints<Bytes<R>>>,
+    // Characters supplied by `lex_source` that haven't been consumed yet; checked before
+    // `reader` by `peek!`/`next!` so fed-in continuation text is seamless to the rest of the
+    // tokenizer.
+    pending: VecDeque<char>,
+    lex_source: Option<Box<dyn LexSource>>,
 
     done: bool,
     position: usize,
     line: usize,
-    col: usize
+    col: usize,
+    // Whether `#`/`//` line comments and `/* ... */` block comments should be yielded as
+    // `TokenContents::Comment` rather than silently discarded like whitespace.
+    emit_comments: bool
 }
 
 // Tokenizers can be created either directly from a string (in which case the desired "filename"
@@ -101,28 +213,81 @@ impl<'a> Tokenizer<Cursor<&'a str>> {
         Tokenizer {
             filename: String::from(filename),
             reader: CodePoints::from(Cursor::new(chars)).peekable(),
+            pending: VecDeque::new(),
+            lex_source: None,
             done: false,
             position: 0,
             line: 1,
-            col: 1
+            col: 1,
+            emit_comments: false
         }
     }
 }
 
-// or from a file.
-impl Tokenizer<File> {
-    pub fn new_from_file(filename: &str) -> io::Result<Tokenizer<File>> {
+// or from a file, which is read in its entirety up front so its encoding can be detected and
+// transcoded to UTF-8 before tokenization begins.
+impl Tokenizer<Cursor<Vec<u8>>> {
+    pub fn new_from_file(filename: &str) -> io::Result<Tokenizer<Cursor<Vec<u8>>>> {
+        Self::new_from_file_with_encoding(filename, None)
+    }
+
+    // Like `new_from_file`, but `encoding` forces a specific `encoding_rs::Encoding` instead of
+    // auto-detecting one. `None` means: honor a leading BOM if present, otherwise fall back to
+    // `chardetng`'s statistical guess.
+    pub fn new_from_file_with_encoding(
+        filename: &str,
+        encoding: Option<&'static Encoding>,
+    ) -> io::Result<Tokenizer<Cursor<Vec<u8>>>> {
+        let mut file = File::open(filename)?;
+        let mut raw = Vec::new();
+        file.read_to_end(&mut raw)?;
+
+        let encoding = encoding
+            .or_else(|| Encoding::for_bom(&raw).map(|(encoding, _bom_length)| encoding))
+            .unwrap_or_else(|| {
+                let mut detector = EncodingDetector::new();
+                detector.feed(&raw, true);
+
+                detector.guess(None, true)
+            });
+
+        let (text, _encoding_used, _had_errors) = encoding.decode(&raw);
+
         Ok(Tokenizer {
             filename: String::from(filename),
-            reader: CodePoints::from(try!(File::open(filename))).peekable(),
+            reader: CodePoints::from(Cursor::new(text.into_owned().into_bytes())).peekable(),
+            pending: VecDeque::new(),
+            lex_source: None,
             done: false,
             position: 0,
             line: 1,
-            col: 1
+            col: 1,
+            emit_comments: false
         })
     }
 }
 
+impl<R: Read> Tokenizer<R> {
+    // Controls whether comments are yielded as `TokenContents::Comment` tokens (`true`) or
+    // silently discarded like whitespace (`false`, the default); useful for tooling like
+    // formatters or doc extractors that need to see comments.
+    pub fn emit_comments(mut self, emit: bool) -> Self {
+        self.emit_comments = emit;
+
+        self
+    }
+
+    // Supplies a `LexSource` the tokenizer can ask for more input when it runs out partway
+    // through a string, backquoted string, or block comment, rather than failing immediately;
+    // this is what turns the tokenizer from a batch reader into something usable one line at a
+    // time in a REPL.
+    pub fn with_lex_source(mut self, source: Box<dyn LexSource>) -> Self {
+        self.lex_source = Some(source);
+
+        self
+    }
+}
+
 //## Tokenization
 //
 // This tokenizer is a simple iterator.
@@ -155,22 +320,27 @@ impl<R: Read> Iterator for Tokenizer<R> {
 
         //
         // Peeks at the next character, automatically failing if an I/O error is encountered.
+        // Characters queued up by a `LexSource` (see `request_more!`) are checked first.
         macro_rules! peek {
             () => {
-                match self.reader.peek() {
-                    Some(result) => {
-                        match result {
-                            &Err(ref error) => {
-                                // We have to manually copy over parts of the error, because we're
-                                // only given a reference.
-                                error!(IO(error.kind(), String::from(error.description())));
-                            },
-                            &Ok(ch) => {
-                                Some(ch)
+                if let Some(&ch) = self.pending.front() {
+                    Some(ch)
+                } else {
+                    match self.reader.peek() {
+                        Some(result) => {
+                            match result {
+                                &Err(ref error) => {
+                                    // We have to manually copy over parts of the error, because we're
+                                    // only given a reference.
+                                    error!(IO(error.kind(), String::from(error.description())));
+                                },
+                                &Ok(ch) => {
+                                    Some(ch)
+                                }
                             }
-                        }
-                    },
-                    None => None,
+                        },
+                        None => None,
+                    }
                 }
             };
         }
@@ -192,31 +362,44 @@ impl<R: Read> Iterator for Tokenizer<R> {
             };
         }
 
-        // Grabs the next character and advances.
+        // Advances the line/col/position counters for a single consumed character.
+        macro_rules! advance_counters {
+            ($ch:expr) => {{
+                if $ch == '\n' {
+                    self.line += 1;
+                    self.col = 1;
+                } else {
+                    self.col += 1;
+                }
+                self.position += $ch.len_utf8();
+            }};
+        }
+
+        // Grabs the next character and advances. Characters queued up by a `LexSource` (see
+        // `request_more!`) are drained first.
         macro_rules! next {
             () => {
-                match self.reader.next() {
-                    Some(result) => {
-                        match result {
-                            Err(ref error) => {
-                                error!(IO(error.kind(), String::from(error.description())));
-                            },
-                            Ok(ch) => {
-                                // We have to advance our counters when we actually consume a
-                                // character; the original position of the token is saved at the
-                                // start of `next()`.
-                                if ch == '\n' {
-                                    self.line += 1;
-                                    self.col = 1;
-                                } else {
-                                    self.col += 1;
+                if let Some(ch) = self.pending.pop_front() {
+                    advance_counters!(ch);
+                    Some(ch)
+                } else {
+                    match self.reader.next() {
+                        Some(result) => {
+                            match result {
+                                Err(ref error) => {
+                                    error!(IO(error.kind(), String::from(error.description())));
+                                },
+                                Ok(ch) => {
+                                    // We have to advance our counters when we actually consume a
+                                    // character; the original position of the token is saved at the
+                                    // start of `next()`.
+                                    advance_counters!(ch);
+                                    Some(ch)
                                 }
-                                self.position += ch.len_utf8();
-                                Some(ch)
                             }
-                        }
-                    },
-                    None => None,
+                        },
+                        None => None,
+                    }
                 }
             };
         }
@@ -228,6 +411,28 @@ impl<R: Read> Iterator for Tokenizer<R> {
             };
         }
 
+        // When the input has run dry inside a string, backquoted string, or block comment, asks
+        // `self.lex_source` (if any) for more text rather than failing immediately. Evaluates to
+        // `true` (and queues the new text into `self.pending`) if more text was supplied, `false`
+        // if the caller should fall back to its usual `IncompleteInput` error.
+        macro_rules! request_more {
+            ($prompt:expr) => {
+                match self.lex_source.as_mut() {
+                    Some(source) => {
+                        let more = source.read($prompt);
+
+                        if more.is_empty() {
+                            false
+                        } else {
+                            self.pending.extend(more.chars());
+                            true
+                        }
+                    },
+                    None => false,
+                }
+            };
+        }
+
         // Advances a character and gives back a single-character token of the given type.
         macro_rules! yield_char {
             ($token_type:ident) => {{
@@ -251,6 +456,39 @@ impl<R: Read> Iterator for Tokenizer<R> {
             };
         }
 
+        // Consumes a run of digits (in the given radix) interspersed with `_` digit separators,
+        // pushing only the digits themselves onto `$buf`. `$any_digit_init` seeds whether a digit
+        // has already been seen before this run started (e.g. the leading digit of a decimal
+        // literal, consumed outside the run). Errors on a separator with no digit immediately
+        // before it (including a trailing separator) via `error!`.
+        macro_rules! scan_digit_run {
+            ($buf:expr, $radix:expr, $any_digit_init:expr) => {{
+                let mut last_was_sep = false;
+                let mut any_digit = $any_digit_init;
+
+                loop {
+                    if let Some(d) = next_if!(|ch2| -> ch2.is_digit($radix)) {
+                        $buf.push(d);
+                        any_digit = true;
+                        last_was_sep = false;
+                    } else if let Some(_) = next_if!(|ch2| -> ch2 == '_') {
+                        if !any_digit || last_was_sep {
+                            error!(InvalidNumber(String::from("misplaced digit separator")));
+                        }
+                        last_was_sep = true;
+                    } else {
+                        break;
+                    }
+                }
+
+                if last_was_sep {
+                    error!(InvalidNumber(String::from("trailing digit separator")));
+                }
+
+                any_digit
+            }};
+        }
+
         let position = self.position;
         let line = self.line;
         let col = self.col;
@@ -263,6 +501,97 @@ impl<R: Read> Iterator for Tokenizer<R> {
             match ch {
                 // Ignore whitespace and allow the loop to continue.
                 ' ' | '\t' => { continue },
+                // `#` starts a line comment that runs to (but doesn't consume) the next newline.
+                '#' => {
+                    let mut contents = String::new();
+
+                    while let Some(next_ch) = next_if!(|ch2| -> ch2 != '\n') {
+                        contents.push(next_ch);
+                    }
+
+                    if self.emit_comments {
+                        token_contents = Comment(contents);
+                    } else {
+                        continue;
+                    }
+                },
+                // `//` is an alternate line comment marker; a lone `/` falls through to the
+                // branches below, and as it isn't an `XID_Start` character it ends up an
+                // `InvalidChar` error.
+                _ if ch == '/' && next_if!(|ch2| -> ch2 == '/').is_some() => {
+                    let mut contents = String::new();
+
+                    while let Some(next_ch) = next_if!(|ch2| -> ch2 != '\n') {
+                        contents.push(next_ch);
+                    }
+
+                    if self.emit_comments {
+                        token_contents = Comment(contents);
+                    } else {
+                        continue;
+                    }
+                },
+                // `/* ... */` block comments nest, so `/* /* */ */` only closes at the outer
+                // `*/`. An unterminated comment errors at the position of the opening `/*`.
+                _ if ch == '/' && next_if!(|ch2| -> ch2 == '*').is_some() => {
+                    let mut contents = String::new();
+                    let mut depth = 1;
+                    // `/` and `*` are one byte and one column each and never end a line, so the
+                    // opening delimiter sits just behind the current position.
+                    let (open_position, open_line, open_col) =
+                        (self.position - 2, self.line, self.col - 2);
+
+                    loop {
+                        if let Some(_) = next_if!(|ch2| -> ch2 == '*') {
+                            if next_if!(|ch2| -> ch2 == '/').is_some() {
+                                depth -= 1;
+
+                                if depth == 0 {
+                                    break;
+                                }
+
+                                contents.push('*');
+                                contents.push('/');
+                            } else {
+                                contents.push('*');
+                            }
+                        } else if let Some(_) = next_if!(|ch2| -> ch2 == '/') {
+                            if next_if!(|ch2| -> ch2 == '*').is_some() {
+                                depth += 1;
+                                contents.push('/');
+                                contents.push('*');
+                            } else {
+                                contents.push('/');
+                            }
+                        } else {
+                            match next!() {
+                                Some(next_ch) => contents.push(next_ch),
+                                None => {
+                                    if request_more!(PromptStyle::ContinueComment) {
+                                        continue;
+                                    }
+
+                                    self.done = true;
+
+                                    return Some(Err(TokenError {
+                                        kind: TokenErrorKind::IncompleteInput(String::from(
+                                            "expected */ to end block comment"
+                                        )),
+                                        position: open_position,
+                                        line: open_line,
+                                        col: open_col
+                                    }));
+                                }
+                            }
+                        }
+                    }
+
+                    if self.emit_comments {
+                        token_contents = Comment(contents);
+                    } else {
+                        continue;
+                    }
+                },
                 // There are several base characters that go through the tokenizer unmolested.
                 '\'' => { token_contents = Quote },
                 ',' => { token_contents = Comma },
@@ -278,30 +607,52 @@ impl<R: Read> Iterator for Tokenizer<R> {
                 '"' => {
                     let mut contents = String::new();
 
-                    while let Some(next_ch) = next_if!(|ch2| -> ch2 != '"') {
-                        if next_ch == '\n' {
-                            error!(InvalidChar('\n', String::from("unexpected newline in string")));
-                        } else if next_ch == '\\' {
-                            if let Some(next_ch) = next!() {
-                                contents.push(match next_ch {
-                                    '\\' => '\\',
-                                    '"' => '"',
-                                    'n' => '\n',
-                                    'r' => '\r',
-                                    't' => '\t',
-                                    _ => { error!(InvalidChar(next_ch, String::from("unknown escaped character"))) },
-                                });
-                            } else {
-                                error!(IncompleteInput(String::from("expected character after \\ in string")));
+                    loop {
+                        match peek!() {
+                            None => {
+                                if request_more!(PromptStyle::ContinueString) {
+                                    continue;
+                                }
+
+                                error!(IncompleteInput(String::from("expected \" to end string")));
+                            },
+                            Some('"') => break,
+                            Some(_) => {
+                                let next_ch = next!().unwrap_or_else(|| unreachable!());
+
+                                if next_ch == '\n' {
+                                    error!(InvalidChar('\n', String::from("unexpected newline in string")));
+                                } else if next_ch == '\\' {
+                                    loop {
+                                        match next!() {
+                                            Some(escaped) => {
+                                                contents.push(match escaped {
+                                                    '\\' => '\\',
+                                                    '"' => '"',
+                                                    'n' => '\n',
+                                                    'r' => '\r',
+                                                    't' => '\t',
+                                                    _ => { error!(InvalidChar(escaped, String::from("unknown escaped character"))) },
+                                                });
+                                                break;
+                                            },
+                                            None => {
+                                                if request_more!(PromptStyle::ContinueString) {
+                                                    continue;
+                                                }
+
+                                                error!(IncompleteInput(String::from("expected character after \\ in string")));
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    contents.push(next_ch);
+                                }
                             }
-                        } else {
-                            contents.push(next_ch);
                         }
                     }
 
-                    if let None = next!() {
-                        error!(IncompleteInput(String::from("expected \" to end string")));
-                    }
+                    consume!(); // the closing `"`
 
                     token_contents = Str(contents);
                 },
@@ -310,54 +661,156 @@ impl<R: Read> Iterator for Tokenizer<R> {
                 '`' => {
                     let mut contents = String::new();
 
-                    while let Some(next_ch) = next_if!(|ch2| -> ch2 != '`') {
-                        if next_ch == '\\' {
-                            if let Some(next_ch) = next!() {
-                                contents.push('\\');
-                                contents.push(next_ch);
-                            } else {
-                                error!(IncompleteInput(String::from("expected character after \\ in string")));
+                    loop {
+                        match peek!() {
+                            None => {
+                                if request_more!(PromptStyle::ContinueBackquote) {
+                                    continue;
+                                }
+
+                                error!(IncompleteInput(String::from("expected ` to end string")));
+                            },
+                            Some('`') => break,
+                            Some(_) => {
+                                let next_ch = next!().unwrap_or_else(|| unreachable!());
+
+                                if next_ch == '\\' {
+                                    loop {
+                                        match next!() {
+                                            Some(escaped) => {
+                                                contents.push('\\');
+                                                contents.push(escaped);
+                                                break;
+                                            },
+                                            None => {
+                                                if request_more!(PromptStyle::ContinueBackquote) {
+                                                    continue;
+                                                }
+
+                                                error!(IncompleteInput(String::from("expected character after \\ in string")));
+                                            }
+                                        }
+                                    }
+                                } else {
+                                    contents.push(next_ch);
+                                }
                             }
-                        } else {
-                            contents.push(next_ch);
                         }
                     }
 
-                    if let None = next!() {
-                        error!(IncompleteInput(String::from("expected ` to end string")));
-                    }
+                    consume!(); // the closing backquote
 
                     token_contents = Str(contents);
                 },
-                _ if ch == '-' || ch.is_digit(10) => {
-                    let mut contents = String::new();
-                    contents.push(ch);
+                // A lone `-` (or `-` followed by anything but a digit) isn't the start of a
+                // number; it falls through to the branches below, and as it isn't an `XID_Start`
+                // character it ends up an `InvalidChar` error.
+                _ if ch.is_digit(10)
+                    || (ch == '-' && matches!(peek!(), Some(c) if c.is_digit(10))) => {
+                    let negative = ch == '-';
+
+                    let first_digit = if negative {
+                        next!().unwrap_or_else(|| unreachable!())
+                    } else {
+                        ch
+                    };
+
+                    // A `0` immediately followed by `x`/`o`/`b` introduces a radix prefix; the
+                    // `0` and marker themselves aren't part of the digits that get parsed.
+                    let mut radix = 10;
+                    let mut has_radix_prefix = false;
+                    let mut digits = String::new();
+
+                    if first_digit == '0' {
+                        if let Some(marker) = next_if!(|ch2| -> val_in!(ch2, 'x', 'o', 'b')) {
+                            radix = match marker {
+                                'x' => 16,
+                                'o' => 8,
+                                _ => 2,
+                            };
+                            has_radix_prefix = true;
+                        }
+                    }
 
-                    while let Some(next_ch) = next_if!(|ch2| -> ch2.is_digit(10)) {
-                        contents.push(next_ch);
+                    if has_radix_prefix {
+                        if !scan_digit_run!(digits, radix, false) {
+                            error!(InvalidNumber(String::from("expected digits after radix prefix")));
+                        }
+                    } else {
+                        digits.push(first_digit);
+                        scan_digit_run!(digits, radix, true);
                     }
 
-                    if let Some(dot_ch) = next_if!(|ch2| -> ch2 == '.') {
-                        contents.push(dot_ch);
+                    let mut is_float = false;
+                    let mut frac_digits = String::new();
+                    let mut exponent = String::new();
 
-                        while let Some(next_ch) = next_if!(|ch2| -> ch2.is_digit(10)) {
-                            contents.push(next_ch);
+                    // Only base-10 literals can have a fractional part or exponent; `0x1.5` has
+                    // no meaning here.
+                    if radix == 10 {
+                        if next_if!(|ch2| -> ch2 == '.').is_some() {
+                            is_float = true;
+                            scan_digit_run!(frac_digits, 10, false);
                         }
 
-                        token_contents = Real(contents.parse::<f64>().unwrap())
+                        if let Some(e_ch) = next_if!(|ch2| -> val_in!(ch2, 'e', 'E')) {
+                            is_float = true;
+
+                            if let Some(sign_ch) = next_if!(|ch2| -> val_in!(ch2, '+', '-')) {
+                                exponent.push(sign_ch);
+                            }
+
+                            if !scan_digit_run!(exponent, 10, false) {
+                                error!(InvalidNumber(String::from("expected digits after exponent")));
+                            }
+
+                            let _ = e_ch;
+                        }
+                    }
+
+                    token_contents = if is_float {
+                        let text = format!(
+                            "{}{}.{}{}",
+                            if negative { "-" } else { "" },
+                            digits,
+                            frac_digits,
+                            if exponent.is_empty() { String::new() } else { format!("e{}", exponent) }
+                        );
+
+                        match text.parse::<f64>() {
+                            Ok(value) => Real(value),
+                            Err(e) => error!(InvalidNumber(format!("{}: {}", text, e))),
+                        }
                     } else {
-                        token_contents = Integer(contents.parse::<i64>().unwrap())
+                        match i64::from_str_radix(&digits, radix) {
+                            Ok(value) => Integer(if negative { -value } else { value }),
+                            Err(e) => error!(InvalidNumber(format!("{}: {}", digits, e))),
+                        }
                     }
                 },
-                _ => {
+                // Identifiers start with a Unicode `XID_Start` code point and continue with
+                // `XID_Continue` ones, then get normalized to NFC; this keeps identifier parsing
+                // deterministic regardless of the script the source is written in.
+                _ if is_xid_start(ch) => {
                     let mut contents = String::new();
                     contents.push(ch);
 
-                    while let Some(next_ch) = next_if!(|ch2| -> !val_in!(ch2, '\'', ',', '{', '}', '[', ']', '(', ')', '\n', ' ', '\t', '"', '`')) {
+                    while let Some(next_ch) = next_if!(|ch2| -> is_xid_continue(ch2)) {
                         contents.push(next_ch);
                     }
 
-                    token_contents = Identifier(contents)
+                    token_contents = Identifier(contents.nfc().collect())
+                },
+                _ => {
+                    let message = match confusable_suggestion(ch) {
+                        Some(suggestion) => format!(
+                            "not a valid identifier-starting character; did you mean `{}`?",
+                            suggestion
+                        ),
+                        None => String::from("not a valid identifier-starting character"),
+                    };
+
+                    error!(InvalidChar(ch, message));
                 }
             }
 
@@ -368,12 +821,39 @@ impl<R: Read> Iterator for Tokenizer<R> {
             self.done = true;
             None
         } else {
-            Some(Ok(Token { contents: token_contents, position: position, line: line, col: col }))
+            Some(Ok(Token {
+                contents: token_contents,
+                position: position,
+                line: line,
+                col: col,
+                end: SourceLocation {
+                    position: self.position,
+                    line: self.line,
+                    col: self.col
+                }
+            }))
         }
     }
 }
 
-#cfg(test)
+#[cfg(test)]
 mod tests {
+    use super::*;
+
+    fn tokenize(input: &str) -> Vec<TokenContents> {
+        Tokenizer::new("<test>", input)
+            .map(|result| result.unwrap().contents)
+            .collect()
+    }
 
+    #[test]
+    fn tokenizes_identifiers_and_integers() {
+        assert_eq!(
+            tokenize("abc 123"),
+            vec![
+                TokenContents::Identifier(String::from("abc")),
+                TokenContents::Integer(123),
+            ]
+        );
+    }
 }
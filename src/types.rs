@@ -4,6 +4,9 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
 use bitflags::bitflags;
 use thiserror::Error;
 
@@ -12,6 +15,9 @@ pub enum Error {
     #[error("expected {expected}, got {actual}")]
     ExpectedType { expected: Type, actual: Type },
 
+    #[error("expected {expected}, got {actual}")]
+    ExpectedOneOf { expected: TypeSet, actual: Type },
+
     #[error("argument {position} invalid: {source}")]
     InvalidArgument { position: usize, source: Box<Error> },
 
@@ -22,21 +28,23 @@ pub enum Error {
     NotEnoughArguments { expected: usize, actual: usize },
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Type {
     Nil,
     Boolean,
     Integer,
+    Float,
+    Char,
     String,
     Identifier,
     Cell,
     Quoted,
 }
 
-impl std::fmt::Display for Type {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Type {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(
             f,
             "{}",
@@ -44,6 +52,8 @@ impl std::fmt::Display for Type {
                 Type::Nil => "nil",
                 Type::Boolean => "boolean",
                 Type::Integer => "integer",
+                Type::Float => "float",
+                Type::Char => "char",
                 Type::String => "string",
                 Type::Identifier => "identifier",
                 Type::Cell => "cell",
@@ -63,24 +73,61 @@ impl Typeable for Type {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// A non-empty set of acceptable types, rendered `a|b|c`, as reported when a value matches none
+/// of the types of a [`TypeSpec::Union`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeSet(Vec<Type>);
+
+impl core::fmt::Display for TypeSet {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (i, type_) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "|")?;
+            }
+            write!(f, "{}", type_)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum TypeSpec {
     Any,
     Base(Type),
+    /// Matches any of `Type`s given, e.g. for a builtin that can accept either an integer or a
+    /// string.
+    Union(Vec<Type>),
     List,
+    /// Matches `Integer` or `Float`, for a builtin that implements the numeric tower's automatic
+    /// integer/float coercion (see `builtins::Number`).
+    Numeric,
 }
 
 impl TypeSpec {
     fn check(&self, actual: Type) -> Result<()> {
         use TypeSpec::*;
 
-        match *self {
+        match self {
             Any => Ok(()),
             Base(expected) => {
-                if actual == expected {
+                if actual == *expected {
+                    Ok(())
+                } else {
+                    Err(Error::ExpectedType {
+                        expected: *expected,
+                        actual,
+                    })
+                }
+            }
+            Union(expected) => {
+                if expected.contains(&actual) {
                     Ok(())
                 } else {
-                    Err(Error::ExpectedType { expected, actual })
+                    Err(Error::ExpectedOneOf {
+                        expected: TypeSet(expected.clone()),
+                        actual,
+                    })
                 }
             }
             List => {
@@ -94,6 +141,16 @@ impl TypeSpec {
                     })
                 }
             }
+            Numeric => {
+                if actual == Type::Integer || actual == Type::Float {
+                    Ok(())
+                } else {
+                    Err(Error::ExpectedOneOf {
+                        expected: TypeSet(vec![Type::Integer, Type::Float]),
+                        actual,
+                    })
+                }
+            }
         }
     }
 }
@@ -149,13 +206,13 @@ impl ArgumentSpecBuilder {
     }
 }
 
-impl std::convert::From<ArgumentSpecBuilder> for ArgumentSpec {
+impl core::convert::From<ArgumentSpecBuilder> for ArgumentSpec {
     fn from(b: ArgumentSpecBuilder) -> Self {
         b.0
     }
 }
 
-impl<T: Into<TypeSpec>> std::convert::From<T> for ArgumentSpec {
+impl<T: Into<TypeSpec>> core::convert::From<T> for ArgumentSpec {
     fn from(t: T) -> Self {
         ArgumentSpec::new(t).into()
     }
@@ -165,6 +222,9 @@ impl<T: Into<TypeSpec>> std::convert::From<T> for ArgumentSpec {
 pub struct Signature {
     pub return_type: Type,
     argument_specs: Vec<ArgumentSpec>,
+    /// Trailing argument specs that may be omitted; a call is valid with anywhere from zero to
+    /// all of these present, in order.
+    optional_argument_specs: Vec<ArgumentSpec>,
     rest_argument_spec: Option<ArgumentSpec>,
 }
 
@@ -173,27 +233,38 @@ impl Signature {
         SignatureBuilder(Self {
             return_type: Type::Nil,
             argument_specs: Vec::new(),
+            optional_argument_specs: Vec::new(),
             rest_argument_spec: None,
         })
     }
 
     pub fn check_arguments_length(&self, actual: usize) -> Result<()> {
-        let expected = self.argument_specs.len();
+        let required = self.argument_specs.len();
+        let allowed = required + self.optional_argument_specs.len();
 
-        if actual < expected {
-            Err(Error::NotEnoughArguments { expected, actual })
-        } else if actual > expected && self.rest_argument_spec.is_none() {
-            Err(Error::TooManyArguments { expected, actual })
+        if actual < required {
+            Err(Error::NotEnoughArguments {
+                expected: required,
+                actual,
+            })
+        } else if actual > allowed && self.rest_argument_spec.is_none() {
+            Err(Error::TooManyArguments {
+                expected: allowed,
+                actual,
+            })
         } else {
             Ok(())
         }
     }
 
     pub fn specs_by_position(&self) -> impl Iterator<Item = &ArgumentSpec> + '_ {
-        let mut arg_specs = self.argument_specs.iter();
+        let mut arg_specs = self
+            .argument_specs
+            .iter()
+            .chain(self.optional_argument_specs.iter());
         let mut arg_spec = arg_specs.next();
 
-        std::iter::from_fn(move || match arg_spec {
+        core::iter::from_fn(move || match arg_spec {
             None => match &self.rest_argument_spec {
                 None => None,
                 Some(spec) => Some(spec),
@@ -228,6 +299,14 @@ impl SignatureBuilder {
         self
     }
 
+    /// Adds a trailing argument that may be omitted from a call; must be added after all
+    /// required `add()`s and before any `add_rest()`.
+    pub fn add_optional(mut self, argument_spec: impl Into<ArgumentSpec>) -> Self {
+        self.0.optional_argument_specs.push(argument_spec.into());
+
+        self
+    }
+
     pub fn add_rest(mut self, argument_spec: impl Into<ArgumentSpec>) -> Self {
         self.0.rest_argument_spec = Some(argument_spec.into());
 
@@ -235,7 +314,7 @@ impl SignatureBuilder {
     }
 }
 
-impl std::convert::From<SignatureBuilder> for Signature {
+impl core::convert::From<SignatureBuilder> for Signature {
     fn from(b: SignatureBuilder) -> Self {
         b.0
     }
@@ -290,6 +369,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn numeric_takes_integer_or_float() -> Result<()> {
+        let spec = TypeSpec::Numeric;
+
+        spec.check(Type::Integer)?;
+        spec.check(Type::Float)?;
+        assert_err_matches_regex!(
+            spec.check(Type::String),
+            "ExpectedOneOf.*Integer.*Float.*String"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn function_taking_any_takes_any_type() -> Result<()> {
         let signature = Signature::new().add_rest(TypeSpec::Any).build();
@@ -362,6 +455,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn union_takes_any_member_type() -> Result<()> {
+        let spec = TypeSpec::Union(vec![Type::Integer, Type::String]);
+
+        spec.check(Type::Integer)?;
+        spec.check(Type::String)?;
+        assert_err_matches_regex!(
+            spec.check(Type::Boolean),
+            "ExpectedOneOf.*Integer.*String.*Boolean"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn function_taking_optional_argument_accepts_it_present_or_absent() -> Result<()> {
+        let signature = Signature::new()
+            .add(Type::Integer)
+            .add_optional(Type::Boolean)
+            .build();
+
+        check_args(&signature, vec![Type::Integer])?;
+        check_args(&signature, vec![Type::Integer, Type::Boolean])?;
+        assert_err_matches_regex!(
+            check_args(
+                &signature,
+                vec![Type::Integer, Type::Boolean, Type::Integer]
+            ),
+            "TooManyArguments.*2.*3"
+        );
+        assert_err_matches_regex!(check_args(&signature, vec![]), "NotEnoughArguments.*1.*0");
+
+        Ok(())
+    }
+
+    #[test]
+    fn function_taking_optional_and_rest_arguments() -> Result<()> {
+        let signature = Signature::new()
+            .add(Type::Integer)
+            .add_optional(Type::Boolean)
+            .add_rest(TypeSpec::Union(vec![Type::Integer, Type::String]))
+            .build();
+
+        check_args(&signature, vec![Type::Integer])?;
+        check_args(&signature, vec![Type::Integer, Type::Boolean])?;
+        check_args(
+            &signature,
+            vec![Type::Integer, Type::Boolean, Type::Integer, Type::String],
+        )?;
+
+        Ok(())
+    }
+
     #[test]
     fn function_taking_mixed_arguments_enforces_types() -> Result<()> {
         let signature = Signature::new()
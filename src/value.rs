@@ -4,9 +4,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::collections::HashMap;
-use std::rc::Rc;
 
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
 use thiserror::Error;
 
 use crate::types::{self, Type, Typeable};
@@ -23,17 +28,50 @@ pub enum Error {
     ExpectedType(Type, Type),
 }
 
-pub type Result<T> = std::result::Result<T, Error>;
+pub type Result<T> = core::result::Result<T, Error>;
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Value {
     Nil,
     Boolean(bool),
     Integer(isize),
+    Float(OrderedFloat),
+    Char(char),
     String(String),
     Identifier(Identifier),
     Cell(Rc<Value>, Rc<Value>),
     Quoted(Rc<Value>),
+    /// Sentinel left in place of a value the parser couldn't make sense of, produced only by the
+    /// error-recovering parse entry points so a partial tree stays walkable. Never produced by
+    /// the normal (non-recovering) parser, which returns an `Err` instead.
+    Error,
+}
+
+/// Wraps `f64` so [`Value`] can derive `Eq`; `NaN` is treated as equal to itself via bit
+/// comparison, which is good enough for a language without IEEE total ordering semantics yet.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug)]
+pub struct OrderedFloat(pub f64);
+
+impl PartialEq for OrderedFloat {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for OrderedFloat {}
+
+impl core::fmt::Display for OrderedFloat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<f64> for OrderedFloat {
+    fn from(f: f64) -> Self {
+        OrderedFloat(f)
+    }
 }
 
 impl Value {
@@ -65,6 +103,20 @@ impl Value {
         }
     }
 
+    pub fn try_as_float(&self) -> Result<f64> {
+        match self {
+            Value::Float(f) => Ok(f.0),
+            _ => Err(Error::ExpectedType(Type::Float, self.type_())),
+        }
+    }
+
+    pub fn try_as_char(&self) -> Result<char> {
+        match self {
+            Value::Char(c) => Ok(*c),
+            _ => Err(Error::ExpectedType(Type::Char, self.type_())),
+        }
+    }
+
     pub fn try_as_cell(&self) -> Result<(&Value, &Value)> {
         match self {
             Value::Cell(ref l, ref r) => Ok((l, r)),
@@ -75,7 +127,7 @@ impl Value {
     pub fn iter_list(&self) -> impl Iterator<Item = Result<&Self>> {
         let mut current = Some(self);
 
-        std::iter::from_fn(move || match current.take() {
+        core::iter::from_fn(move || match current.take() {
             None => None,
             Some(Value::Nil) => None,
             Some(val) => match val.try_as_cell() {
@@ -101,7 +153,7 @@ impl Value {
     pub fn iter_list_rc(cell: Rc<Value>) -> impl Iterator<Item = Result<Rc<Self>>> {
         let mut current = Some(cell);
 
-        std::iter::from_fn(move || match &*current.take()? {
+        core::iter::from_fn(move || match &*current.take()? {
             Value::Nil => None,
             val => match val.try_as_cell_rc() {
                 Ok((l, r)) => {
@@ -115,6 +167,88 @@ impl Value {
             },
         })
     }
+
+    /// Structurally matches `self` (the scrutinee of a `match` clause) against `pattern`,
+    /// returning the values captured by its binding identifiers, in the same left-to-right order
+    /// [`pattern_binding_names`] walks them in, or `None` if the pattern doesn't match.
+    ///
+    /// An `Identifier` pattern binds whatever lines up with it (except `_`, which matches
+    /// anything without binding); an `Integer`/`Float`/`Char`/`String`/`Boolean`/`Nil` literal
+    /// matches by structural equality; a `Cell` requires `self` to also be a cell and recursively
+    /// matches car against car and cdr against cdr; and a `Quoted` identifier (a rest-marker like
+    /// `@rest`) matches anything: as the last element of a list pattern it binds the rest of the
+    /// list from that point on (`nil` if nothing is left), and anywhere else it binds whatever
+    /// `self` holds at that point.
+    pub fn match_pattern(&self, pattern: &Value) -> Option<Vec<Value>> {
+        let mut bindings = Vec::new();
+
+        if match_pattern_into(self, pattern, &mut bindings) {
+            Some(bindings)
+        } else {
+            None
+        }
+    }
+}
+
+fn match_pattern_into(subject: &Value, pattern: &Value, bindings: &mut Vec<Value>) -> bool {
+    match pattern {
+        Value::Identifier(name) if &**name == "_" => true,
+        Value::Identifier(_) => {
+            bindings.push(subject.clone());
+            true
+        }
+        Value::Quoted(inner) => match &**inner {
+            Value::Identifier(_) => {
+                bindings.push(subject.clone());
+                true
+            }
+            _ => subject == pattern,
+        },
+        Value::Cell(pl, pr) => {
+            // A rest-marker as the last element of a list pattern takes the whole remaining tail.
+            if let (Value::Quoted(inner), Value::Nil) = (&**pl, &**pr) {
+                if let Value::Identifier(_) = &**inner {
+                    bindings.push(subject.clone());
+                    return true;
+                }
+            }
+
+            match subject.try_as_cell() {
+                Ok((sl, sr)) => {
+                    match_pattern_into(sl, pl, bindings) && match_pattern_into(sr, pr, bindings)
+                }
+                Err(_) => false,
+            }
+        }
+        _ => subject == pattern,
+    }
+}
+
+/// The binding identifiers a `match` pattern introduces, in the same left-to-right order
+/// [`Value::match_pattern`] returns their captured values in, so the compiler can allocate one
+/// register per name before the `match` they belong to runs.
+pub fn pattern_binding_names(pattern: &Value) -> Vec<&Identifier> {
+    let mut names = Vec::new();
+    collect_pattern_binding_names(pattern, &mut names);
+
+    names
+}
+
+fn collect_pattern_binding_names<'a>(pattern: &'a Value, names: &mut Vec<&'a Identifier>) {
+    match pattern {
+        Value::Identifier(name) if &**name == "_" => {}
+        Value::Identifier(name) => names.push(name),
+        Value::Quoted(inner) => {
+            if let Value::Identifier(name) = &**inner {
+                names.push(name);
+            }
+        }
+        Value::Cell(l, r) => {
+            collect_pattern_binding_names(l, names);
+            collect_pattern_binding_names(r, names);
+        }
+        _ => {}
+    }
 }
 
 impl types::Typeable for Value {
@@ -123,10 +257,13 @@ impl types::Typeable for Value {
             Value::Nil => Type::Nil,
             Value::Boolean(_) => Type::Boolean,
             Value::Integer(_) => Type::Integer,
+            Value::Float(_) => Type::Float,
+            Value::Char(_) => Type::Char,
             Value::String(_) => Type::String,
             Value::Identifier(_) => Type::Identifier,
             Value::Cell(_, _) => Type::Cell,
             Value::Quoted(_) => Type::Quoted,
+            Value::Error => unreachable!("Value::Error is a parse diagnostic sentinel, not a typeable value"),
         }
     }
 }
@@ -134,8 +271,8 @@ impl types::Typeable for Value {
 fn format_cell_contents<'a>(
     mut left: &'a Rc<Value>,
     mut right: &'a Rc<Value>,
-    f: &mut std::fmt::Formatter<'_>,
-) -> std::fmt::Result {
+    f: &mut core::fmt::Formatter<'_>,
+) -> core::fmt::Result {
     loop {
         match (&**left, &**right) {
             (lv, &Value::Nil) => break write!(f, "{}", lv),
@@ -144,18 +281,22 @@ fn format_cell_contents<'a>(
                 left = &ilv;
                 right = &irv;
             }
-            _ => todo!("left: {:?}, right: {:?}", left, right),
+            // An improper pair (cdr is neither `Nil` nor another `Cell`) renders as `(a . b)`, or
+            // `(1 2 . 3)` when it's the tail of an otherwise-proper list.
+            (lv, rv) => break write!(f, "{} . {}", lv, rv),
         }
     }
 }
 
-impl std::fmt::Display for Value {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Value {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Value::Nil => write!(f, "nil"),
             Value::Boolean(b) => write!(f, "{}", b),
             Value::Identifier(i) => write!(f, "{}", i),
             Value::Integer(i) => write!(f, "{}", i),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Char(c) => write!(f, "{:?}", c),
             Value::String(s) => write!(f, "{:?}", s),
             Value::Cell(l, r) => {
                 write!(f, "(")?;
@@ -163,52 +304,95 @@ impl std::fmt::Display for Value {
                 write!(f, ")")
             }
             Value::Quoted(v) => write!(f, "'{}", v),
+            Value::Error => write!(f, "<error>"),
         }
     }
 }
 
-impl std::convert::From<bool> for Value {
+impl core::convert::From<bool> for Value {
     fn from(b: bool) -> Self {
         Value::Boolean(b)
     }
 }
 
-impl std::convert::From<&str> for Value {
+impl core::convert::From<&str> for Value {
     fn from(s: &str) -> Self {
         Value::String(s.to_string())
     }
 }
 
-impl std::convert::From<isize> for Value {
+impl core::convert::From<isize> for Value {
     fn from(i: isize) -> Self {
         Value::Integer(i.into())
     }
 }
 
+impl core::convert::From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Value::Float(f.into())
+    }
+}
+
+impl core::convert::From<char> for Value {
+    fn from(c: char) -> Self {
+        Value::Char(c)
+    }
+}
+
+/// An opaque, stable handle to a node interned into a [`ContextMap`]. Unlike the node's `Rc`
+/// pointer, a `NodeId` stays valid (and meaningful) for the map's whole lifetime even if every
+/// other `Rc` to that node is dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NodeId(usize);
+
+/// A map from `Value` nodes (keyed by `Rc` identity, not structural equality — two `Rc`s wrapping
+/// equal `Value`s are still distinct keys) to an annotation `T`, used by the parser to record
+/// each node's source span and by the compiler to look spans back up.
+///
+/// Each key's `Rc<Value>` is retained for the life of the map and interned under a stable
+/// [`NodeId`] at insert time, so — unlike a `HashMap<*const Value, T>` keyed on a bare pointer —
+/// an entry's identity can never be silently corrupted by an unrelated `Value` being allocated at
+/// the same address after the original `Rc` is dropped elsewhere. This also makes iteration safe,
+/// since every node it yields is guaranteed to still be alive.
 #[derive(Debug)]
-pub struct ContextMap<T>(HashMap<*const Value, T>);
+pub struct ContextMap<T> {
+    ids_by_ptr: HashMap<*const Value, NodeId>,
+    entries: Vec<(Rc<Value>, T)>,
+}
 
 impl<T> ContextMap<T> {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self {
+            ids_by_ptr: HashMap::new(),
+            entries: Vec::new(),
+        }
     }
 
+    /// Inserts `v` under `k`'s identity, returning the previous value if `k` (by `Rc` identity)
+    /// was already present.
     pub fn insert(&mut self, k: &Rc<Value>, v: T) -> Option<T> {
-        self.0.insert(Rc::as_ptr(k), v)
+        match self.ids_by_ptr.get(&Rc::as_ptr(k)) {
+            Some(id) => Some(core::mem::replace(&mut self.entries[id.0].1, v)),
+            None => {
+                let id = NodeId(self.entries.len());
+                self.ids_by_ptr.insert(Rc::as_ptr(k), id);
+                self.entries.push((k.clone(), v));
+
+                None
+            }
+        }
     }
 
-    pub unsafe fn iter(&self) -> impl Iterator<Item = (&Value, &T)> {
-        self.0
-            .iter()
-            .map(|(k, v)| (unsafe { k.as_ref().unwrap() }, v))
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &T)> {
+        self.entries.iter().map(|(k, v)| (k.as_ref(), v))
     }
 }
 
-impl<T> std::ops::Index<&Rc<Value>> for ContextMap<T> {
+impl<T> core::ops::Index<&Rc<Value>> for ContextMap<T> {
     type Output = T;
 
-    fn index(&self, i: &Rc<Value>) -> &T {
-        &self.0[&Rc::as_ptr(&i)]
+    fn index(&self, k: &Rc<Value>) -> &T {
+        &self.entries[self.ids_by_ptr[&Rc::as_ptr(k)].0].1
     }
 }
 
@@ -217,34 +401,34 @@ impl<T> std::ops::Index<&Rc<Value>> for ContextMap<T> {
 macro_rules! value {
     ((@$quoted_first:tt $($inner:tt)+)) => {
         $crate::value::Value::Cell(
-            std::rc::Rc::new($crate::value::Value::Quoted(std::rc::Rc::new(value!($quoted_first)))),
-            std::rc::Rc::new(value!(($($inner)+))),
+            $crate::value::Rc::new($crate::value::Value::Quoted($crate::value::Rc::new(value!($quoted_first)))),
+            $crate::value::Rc::new(value!(($($inner)+))),
         )
     };
 
     ((@$quoted_inner:tt)) => {
         $crate::value::Value::Cell(
-            std::rc::Rc::new($crate::value::Value::Quoted(std::rc::Rc::new(value!($quoted_inner)))),
-            std::rc::Rc::new($crate::value::Value::Nil),
+            $crate::value::Rc::new($crate::value::Value::Quoted($crate::value::Rc::new(value!($quoted_inner)))),
+            $crate::value::Rc::new($crate::value::Value::Nil),
         )
     };
 
     (($first:tt $($inner:tt)+)) => {
         $crate::value::Value::Cell(
-            std::rc::Rc::new(value!($first)),
-            std::rc::Rc::new(value!(($($inner)+))),
+            $crate::value::Rc::new(value!($first)),
+            $crate::value::Rc::new(value!(($($inner)+))),
         )
     };
 
     (($inner:tt)) => {
         $crate::value::Value::Cell(
-            std::rc::Rc::new(value!($inner)),
-            std::rc::Rc::new($crate::value::Value::Nil),
+            $crate::value::Rc::new(value!($inner)),
+            $crate::value::Rc::new($crate::value::Value::Nil),
         )
     };
 
     (@$quoted:tt) => {
-        $crate::value::Value::Quoted(std::rc::Rc::new(value!($quoted)))
+        $crate::value::Value::Quoted($crate::value::Rc::new(value!($quoted)))
     };
 
     (nil) => {
@@ -276,6 +460,273 @@ macro_rules! value {
     };
 }
 
+const TAG_NIL: u8 = 0x00;
+const TAG_BOOLEAN: u8 = 0x01;
+const TAG_INTEGER: u8 = 0x02;
+const TAG_FLOAT: u8 = 0x03;
+const TAG_CHAR: u8 = 0x04;
+const TAG_STRING: u8 = 0x05;
+const TAG_IDENTIFIER: u8 = 0x06;
+const TAG_CELL: u8 = 0x07;
+const TAG_QUOTED: u8 = 0x08;
+const TAG_ERROR: u8 = 0x09;
+/// Not a `Value` variant of its own; stands in for an `Rc` that's already been written earlier in
+/// the stream, carrying the id [`write_rc`] assigned it instead of re-emitting its contents.
+const TAG_BACK_REF: u8 = 0x0a;
+
+#[derive(Error, Debug, Eq, PartialEq)]
+pub enum DecodeError {
+    #[error("unexpected end of byte stream")]
+    UnexpectedEof,
+    #[error("invalid value tag: {0:#04x}")]
+    InvalidTag(u8),
+    #[error("invalid UTF-8 in string payload")]
+    InvalidUtf8,
+    #[error("invalid char codepoint: {0:#x}")]
+    InvalidChar(u32),
+    #[error("back-reference {0} has no matching earlier node")]
+    DanglingBackRef(u32),
+}
+
+impl Value {
+    /// Encodes this value, and everything it points to, into Scarab's self-describing binary wire
+    /// format, so a parsed program or a piece of VM state can be written to disk or shipped across
+    /// a process boundary and read back with [`Value::from_bytes`].
+    ///
+    /// Structural sharing in the `Rc<Value>` graph is preserved: an `Rc` that's reachable more
+    /// than once from `self` is only written out the first time it's seen, with later occurrences
+    /// replaced by a compact back-reference, so round-tripping a DAG doesn't blow up into an
+    /// exponential tree.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut seen = HashMap::new();
+
+        write_value(self, &mut out, &mut seen);
+
+        out
+    }
+
+    /// Decodes a value previously produced by [`Value::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> core::result::Result<Value, DecodeError> {
+        let mut cursor = Cursor { bytes, pos: 0 };
+        let mut nodes = Vec::new();
+
+        read_value(&mut cursor, &mut nodes)
+    }
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>, seen: &mut HashMap<*const Value, u32>) {
+    match value {
+        Value::Nil => out.push(TAG_NIL),
+        Value::Boolean(b) => {
+            out.push(TAG_BOOLEAN);
+            out.push(*b as u8);
+        }
+        Value::Integer(i) => {
+            out.push(TAG_INTEGER);
+            write_leb128_i64(*i as i64, out);
+        }
+        Value::Float(f) => {
+            out.push(TAG_FLOAT);
+            out.extend_from_slice(&f.0.to_le_bytes());
+        }
+        Value::Char(c) => {
+            out.push(TAG_CHAR);
+            write_leb128_u64(*c as u64, out);
+        }
+        Value::String(s) => {
+            out.push(TAG_STRING);
+            write_string(s, out);
+        }
+        Value::Identifier(i) => {
+            out.push(TAG_IDENTIFIER);
+            write_string(i, out);
+        }
+        Value::Cell(l, r) => {
+            out.push(TAG_CELL);
+            write_rc(l, out, seen);
+            write_rc(r, out, seen);
+        }
+        Value::Quoted(v) => {
+            out.push(TAG_QUOTED);
+            write_rc(v, out, seen);
+        }
+        Value::Error => out.push(TAG_ERROR),
+    }
+}
+
+/// Writes an `Rc<Value>` that's reachable from a `Cell` or `Quoted` slot, emitting a
+/// [`TAG_BACK_REF`] instead of the full node if this exact `Rc` (by pointer identity, the same
+/// trick `ContextMap` uses) has already been written.
+fn write_rc(rc: &Rc<Value>, out: &mut Vec<u8>, seen: &mut HashMap<*const Value, u32>) {
+    let ptr = Rc::as_ptr(rc);
+
+    if let Some(&id) = seen.get(&ptr) {
+        out.push(TAG_BACK_REF);
+        write_leb128_u64(id as u64, out);
+        return;
+    }
+
+    seen.insert(ptr, seen.len() as u32);
+    write_value(rc, out, seen);
+}
+
+fn write_leb128_u64(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_leb128_i64(value: i64, out: &mut Vec<u8>) {
+    // Zigzag so small negative numbers still encode in few bytes.
+    let zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    write_leb128_u64(zigzag, out);
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) {
+    write_leb128_u64(s.len() as u64, out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn read_value(
+    cursor: &mut Cursor,
+    nodes: &mut Vec<Rc<Value>>,
+) -> core::result::Result<Value, DecodeError> {
+    match cursor.read_u8()? {
+        TAG_NIL => Ok(Value::Nil),
+        TAG_BOOLEAN => Ok(Value::Boolean(cursor.read_u8()? != 0)),
+        TAG_INTEGER => Ok(Value::Integer(cursor.read_leb128_i64()? as isize)),
+        TAG_FLOAT => {
+            let bytes = cursor.read_bytes(8)?;
+
+            Ok(Value::Float(
+                f64::from_le_bytes(bytes.try_into().unwrap()).into(),
+            ))
+        }
+        TAG_CHAR => {
+            let codepoint = cursor.read_leb128_u64()? as u32;
+
+            char::from_u32(codepoint)
+                .map(Value::Char)
+                .ok_or(DecodeError::InvalidChar(codepoint))
+        }
+        TAG_STRING => Ok(Value::String(cursor.read_string()?)),
+        TAG_IDENTIFIER => Ok(Value::Identifier(cursor.read_string()?)),
+        TAG_CELL => {
+            let l = read_rc(cursor, nodes)?;
+            let r = read_rc(cursor, nodes)?;
+
+            Ok(Value::Cell(l, r))
+        }
+        TAG_QUOTED => Ok(Value::Quoted(read_rc(cursor, nodes)?)),
+        TAG_ERROR => Ok(Value::Error),
+        tag => Err(DecodeError::InvalidTag(tag)),
+    }
+}
+
+/// Reads an `Rc<Value>` written by [`write_rc`], reconstructing a shared `Rc` (rather than a
+/// fresh copy) when the tag is a [`TAG_BACK_REF`].
+///
+/// New nodes are assigned the next id and pushed into `nodes` *before* their contents are
+/// decoded, mirroring the order [`write_rc`] assigns ids in, so a back-reference further down the
+/// stream resolves to the right node.
+fn read_rc(
+    cursor: &mut Cursor,
+    nodes: &mut Vec<Rc<Value>>,
+) -> core::result::Result<Rc<Value>, DecodeError> {
+    if cursor.peek_u8()? == TAG_BACK_REF {
+        cursor.read_u8()?;
+        let id = cursor.read_leb128_u64()? as usize;
+
+        return nodes
+            .get(id)
+            .cloned()
+            .ok_or(DecodeError::DanglingBackRef(id as u32));
+    }
+
+    let id = nodes.len();
+    nodes.push(Rc::new(Value::Nil));
+
+    let rc = Rc::new(read_value(cursor, nodes)?);
+    nodes[id] = rc.clone();
+
+    Ok(rc)
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek_u8(&self) -> core::result::Result<u8, DecodeError> {
+        self.bytes
+            .get(self.pos)
+            .copied()
+            .ok_or(DecodeError::UnexpectedEof)
+    }
+
+    fn read_u8(&mut self) -> core::result::Result<u8, DecodeError> {
+        let byte = self.peek_u8()?;
+        self.pos += 1;
+
+        Ok(byte)
+    }
+
+    fn read_leb128_u64(&mut self) -> core::result::Result<u64, DecodeError> {
+        let mut result = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+
+            shift += 7;
+        }
+    }
+
+    fn read_leb128_i64(&mut self) -> core::result::Result<i64, DecodeError> {
+        let zigzag = self.read_leb128_u64()?;
+
+        Ok(((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> core::result::Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    fn read_string(&mut self) -> core::result::Result<String, DecodeError> {
+        let len = self.read_leb128_u64()? as usize;
+        let bytes = self.read_bytes(len)?;
+
+        core::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -386,6 +837,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn improper_cell_display() {
+        snapshot!(
+            format!(
+                "{}",
+                Value::Cell(Rc::new(Value::Integer(1)), Rc::new(Value::Integer(2)))
+            ),
+            "(1 . 2)"
+        );
+
+        snapshot!(
+            format!(
+                "{}",
+                Value::Cell(
+                    Rc::new(Value::Integer(1)),
+                    Rc::new(Value::Cell(
+                        Rc::new(Value::Integer(2)),
+                        Rc::new(Value::Integer(3))
+                    ))
+                )
+            ),
+            "(1 2 . 3)"
+        );
+    }
+
     #[test]
     fn cell_macro() {
         assert_eq!(
@@ -495,4 +971,107 @@ mod tests {
         assert_eq!(iter.next(), Some(Ok(&Value::Integer(4))));
         assert_err_matches_regex!(iter.next().unwrap(), "ExpectedType.*String");
     }
+
+    #[test]
+    fn bytes_round_trip_every_value_variant() {
+        let values = vec![
+            Value::Nil,
+            Value::Boolean(true),
+            Value::Integer(-42),
+            Value::Float(3.5.into()),
+            Value::Char('λ'),
+            Value::String("hi".to_string()),
+            Value::Identifier(identifier("foo")),
+            value!((1 "a" (2 3))),
+            value!(@(123 @(def @123))),
+            Value::Error,
+        ];
+
+        for value in values {
+            assert_eq!(Value::from_bytes(&value.to_bytes()).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip_preserves_sharing() {
+        let shared = Rc::new(value!((1 2)));
+        let list = Value::Cell(
+            shared.clone(),
+            Rc::new(Value::Cell(shared, Rc::new(Value::Nil))),
+        );
+
+        let bytes = list.to_bytes();
+        // The shared sublist is written once, plus one back-reference tag and its id.
+        assert_eq!(bytes.iter().filter(|&&b| b == TAG_BACK_REF).count(), 1);
+
+        assert_eq!(Value::from_bytes(&bytes).unwrap(), list);
+    }
+
+    #[test]
+    fn bytes_decode_rejects_an_unknown_tag() {
+        assert_eq!(Value::from_bytes(&[0xff]), Err(DecodeError::InvalidTag(0xff)));
+    }
+
+    #[test]
+    fn bytes_decode_rejects_a_truncated_stream() {
+        assert_eq!(
+            Value::from_bytes(&[TAG_CELL, TAG_INTEGER]),
+            Err(DecodeError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn bytes_decode_rejects_a_dangling_back_reference() {
+        assert_eq!(
+            Value::from_bytes(&[TAG_QUOTED, TAG_BACK_REF, 0]),
+            Err(DecodeError::DanglingBackRef(0))
+        );
+    }
+
+    #[test]
+    fn match_pattern_binds_an_identifier() {
+        assert_eq!(
+            value!(4567).match_pattern(&value!(x)),
+            Some(vec![value!(4567)])
+        );
+    }
+
+    #[test]
+    fn match_pattern_underscore_matches_without_binding() {
+        assert_eq!(value!(4567).match_pattern(&value!(_)), Some(vec![]));
+    }
+
+    #[test]
+    fn match_pattern_literal_requires_equality() {
+        assert_eq!(value!(4567).match_pattern(&value!(4567)), Some(vec![]));
+        assert_eq!(value!(4567).match_pattern(&value!(89)), None);
+    }
+
+    #[test]
+    fn match_pattern_cell_recurses_and_binds_in_order() {
+        assert_eq!(
+            value!((1 2 3)).match_pattern(&value!((a b c))),
+            Some(vec![value!(1), value!(2), value!(3)])
+        );
+        assert_eq!(value!((1 2 3)).match_pattern(&value!((a b))), None);
+        assert_eq!(value!(4567).match_pattern(&value!((a b))), None);
+    }
+
+    #[test]
+    fn match_pattern_rest_marker_binds_the_remaining_tail() {
+        assert_eq!(
+            value!((1 2 3)).match_pattern(&value!((a @rest))),
+            Some(vec![value!(1), value!((2 3))])
+        );
+    }
+
+    #[test]
+    fn pattern_binding_names_walks_left_to_right() {
+        let names: Vec<&str> = pattern_binding_names(&value!((a _ (b @rest))))
+            .into_iter()
+            .map(String::as_str)
+            .collect();
+
+        assert_eq!(names, vec!["a", "b", "rest"]);
+    }
 }
@@ -0,0 +1,522 @@
+// Copyright (c) Jesse Weaver, 2021
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Binary encoding for a stream of [`Instruction`]s, so a compiled program can be written to disk
+//! and loaded back by the VM instead of only ever living as an in-process `Vec`.
+//!
+//! Each instruction is one opcode byte followed by its operands: register ids as single bytes,
+//! counts as LEB128, and immediate [`Value`]s tagged by type.
+
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use thiserror::Error;
+
+use crate::value::Value;
+#[cfg(feature = "disasm")]
+use crate::vm::code::Pc;
+use crate::vm::code::{Instruction, PcOffset, RegisterId, RegisterOffset};
+
+const OP_ALLOC_REGISTERS: u8 = 0x00;
+const OP_LOAD_IMMEDIATE: u8 = 0x01;
+const OP_CALL_INTERNAL: u8 = 0x02;
+const OP_CALL_FUNCTION: u8 = 0x03;
+const OP_RETURN: u8 = 0x04;
+const OP_COPY: u8 = 0x05;
+const OP_JUMP_IF: u8 = 0x06;
+
+const VALUE_NIL: u8 = 0x00;
+const VALUE_BOOLEAN: u8 = 0x01;
+const VALUE_INTEGER: u8 = 0x02;
+const VALUE_FLOAT: u8 = 0x03;
+const VALUE_CHAR: u8 = 0x04;
+const VALUE_STRING: u8 = 0x05;
+const VALUE_IDENTIFIER: u8 = 0x06;
+const VALUE_CELL: u8 = 0x07;
+const VALUE_QUOTED: u8 = 0x08;
+const VALUE_ERROR: u8 = 0x09;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("unexpected end of bytecode stream")]
+    UnexpectedEof,
+    #[error("invalid opcode: {0:#04x}")]
+    InvalidOpcode(u8),
+    #[error("invalid value tag: {0:#04x}")]
+    InvalidValueTag(u8),
+    #[error("invalid UTF-8 in string operand")]
+    InvalidUtf8,
+    #[error("invalid char codepoint: {0:#x}")]
+    InvalidChar(u32),
+    #[error("register offset out of range: {0}")]
+    InvalidRegisterOffset(i64),
+    #[error("jump distance out of range: {0}")]
+    InvalidPcOffset(i64),
+}
+
+/// Encodes a sequence of instructions into their compact binary form.
+pub fn encode(instructions: &[Instruction]) -> Vec<u8> {
+    let mut out = Vec::new();
+
+    for instruction in instructions {
+        write_instruction(instruction, &mut out);
+    }
+
+    out
+}
+
+/// Decodes a byte stream produced by [`encode`] back into a sequence of instructions.
+pub fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, DecodeError> {
+    let mut cursor = Cursor { bytes, pos: 0 };
+    let mut instructions = Vec::new();
+
+    while cursor.pos < cursor.bytes.len() {
+        instructions.push(read_instruction(&mut cursor)?);
+    }
+
+    Ok(instructions)
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DisasmError {
+    #[error("invalid opcode {opcode:#04x} at offset {offset}")]
+    InvalidOpcode { opcode: u8, offset: usize },
+    #[error("truncated instruction starting at offset {offset}")]
+    Truncated { offset: usize },
+}
+
+/// Walks an encoded instruction stream and re-derives each instruction's textual form (the same
+/// one the compiler's own tests snapshot, e.g. `call + 0 3`), pairing each with the `Pc` it starts
+/// at.
+#[cfg(feature = "disasm")]
+pub fn disasm(bytes: &[u8]) -> Result<Vec<(Pc, String)>, DisasmError> {
+    let mut cursor = Cursor { bytes, pos: 0 };
+    let mut out = Vec::new();
+    let mut pc: Pc = 0;
+
+    while cursor.pos < cursor.bytes.len() {
+        let start = cursor.pos;
+
+        let instruction = read_instruction(&mut cursor).map_err(|e| match e {
+            DecodeError::InvalidOpcode(opcode) => DisasmError::InvalidOpcode {
+                opcode,
+                offset: start,
+            },
+            _ => DisasmError::Truncated { offset: start },
+        })?;
+
+        out.push((pc, format!("{}", instruction)));
+        pc += 1;
+    }
+
+    Ok(out)
+}
+
+fn write_instruction(instruction: &Instruction, out: &mut Vec<u8>) {
+    match instruction {
+        Instruction::AllocRegisters { count } => {
+            out.push(OP_ALLOC_REGISTERS);
+            write_register_offset(*count, out);
+        }
+        Instruction::LoadImmediate { dest, value } => {
+            out.push(OP_LOAD_IMMEDIATE);
+            write_register_id(*dest, out);
+            write_value(value, out);
+        }
+        Instruction::CallInternal {
+            ident,
+            base,
+            num_args,
+        } => {
+            out.push(OP_CALL_INTERNAL);
+            write_string(ident, out);
+            write_register_id(*base, out);
+            write_register_offset(*num_args, out);
+        }
+        Instruction::CallFunction {
+            target,
+            base,
+            num_args,
+        } => {
+            out.push(OP_CALL_FUNCTION);
+            write_leb128_u64(*target as u64, out);
+            write_register_id(*base, out);
+            write_register_offset(*num_args, out);
+        }
+        Instruction::Return { src } => {
+            out.push(OP_RETURN);
+            write_register_id(*src, out);
+        }
+        Instruction::Copy { dest, src } => {
+            out.push(OP_COPY);
+            write_register_id(*dest, out);
+            write_register_id(*src, out);
+        }
+        Instruction::JumpIf { cond, distance } => {
+            out.push(OP_JUMP_IF);
+            write_register_id(*cond, out);
+            write_leb128_i64(*distance as i64, out);
+        }
+    }
+}
+
+fn read_instruction(cursor: &mut Cursor) -> Result<Instruction, DecodeError> {
+    match cursor.read_u8()? {
+        OP_ALLOC_REGISTERS => Ok(Instruction::AllocRegisters {
+            count: cursor.read_register_offset()?,
+        }),
+        OP_LOAD_IMMEDIATE => Ok(Instruction::LoadImmediate {
+            dest: cursor.read_register_id()?,
+            value: cursor.read_value()?,
+        }),
+        OP_CALL_INTERNAL => Ok(Instruction::CallInternal {
+            ident: cursor.read_string()?,
+            base: cursor.read_register_id()?,
+            num_args: cursor.read_register_offset()?,
+        }),
+        OP_CALL_FUNCTION => Ok(Instruction::CallFunction {
+            target: cursor.read_leb128_u64()? as usize,
+            base: cursor.read_register_id()?,
+            num_args: cursor.read_register_offset()?,
+        }),
+        OP_RETURN => Ok(Instruction::Return {
+            src: cursor.read_register_id()?,
+        }),
+        OP_COPY => Ok(Instruction::Copy {
+            dest: cursor.read_register_id()?,
+            src: cursor.read_register_id()?,
+        }),
+        OP_JUMP_IF => Ok(Instruction::JumpIf {
+            cond: cursor.read_register_id()?,
+            distance: cursor.read_pc_offset()?,
+        }),
+        op => Err(DecodeError::InvalidOpcode(op)),
+    }
+}
+
+fn write_register_id(id: RegisterId, out: &mut Vec<u8>) {
+    out.push(id);
+}
+
+fn write_register_offset(offset: RegisterOffset, out: &mut Vec<u8>) {
+    write_leb128_i64(offset as i64, out);
+}
+
+fn write_leb128_u64(mut value: u64, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            out.push(byte);
+            break;
+        }
+
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_leb128_i64(value: i64, out: &mut Vec<u8>) {
+    // Zigzag so small negative numbers still encode in few bytes.
+    let zigzag = ((value << 1) ^ (value >> 63)) as u64;
+    write_leb128_u64(zigzag, out);
+}
+
+fn write_string(s: &str, out: &mut Vec<u8>) {
+    write_leb128_u64(s.len() as u64, out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Nil => out.push(VALUE_NIL),
+        Value::Boolean(b) => {
+            out.push(VALUE_BOOLEAN);
+            out.push(*b as u8);
+        }
+        Value::Integer(i) => {
+            out.push(VALUE_INTEGER);
+            write_leb128_i64(*i as i64, out);
+        }
+        Value::Float(f) => {
+            out.push(VALUE_FLOAT);
+            out.extend_from_slice(&f.0.to_le_bytes());
+        }
+        Value::Char(c) => {
+            out.push(VALUE_CHAR);
+            write_leb128_u64(*c as u64, out);
+        }
+        Value::String(s) => {
+            out.push(VALUE_STRING);
+            write_string(s, out);
+        }
+        Value::Identifier(i) => {
+            out.push(VALUE_IDENTIFIER);
+            write_string(i, out);
+        }
+        Value::Cell(l, r) => {
+            out.push(VALUE_CELL);
+            write_value(l, out);
+            write_value(r, out);
+        }
+        Value::Quoted(v) => {
+            out.push(VALUE_QUOTED);
+            write_value(v, out);
+        }
+        Value::Error => out.push(VALUE_ERROR),
+    }
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        let byte = *self.bytes.get(self.pos).ok_or(DecodeError::UnexpectedEof)?;
+        self.pos += 1;
+
+        Ok(byte)
+    }
+
+    fn read_leb128_u64(&mut self) -> Result<u64, DecodeError> {
+        let mut result = 0u64;
+        let mut shift = 0;
+
+        loop {
+            let byte = self.read_u8()?;
+            result |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(result);
+            }
+
+            shift += 7;
+        }
+    }
+
+    fn read_leb128_i64(&mut self) -> Result<i64, DecodeError> {
+        let zigzag = self.read_leb128_u64()?;
+
+        Ok(((zigzag >> 1) as i64) ^ -((zigzag & 1) as i64))
+    }
+
+    fn read_register_id(&mut self) -> Result<RegisterId, DecodeError> {
+        self.read_u8()
+    }
+
+    fn read_register_offset(&mut self) -> Result<RegisterOffset, DecodeError> {
+        let value = self.read_leb128_i64()?;
+
+        RegisterOffset::try_from(value).map_err(|_| DecodeError::InvalidRegisterOffset(value))
+    }
+
+    fn read_pc_offset(&mut self) -> Result<PcOffset, DecodeError> {
+        let value = self.read_leb128_i64()?;
+
+        PcOffset::try_from(value).map_err(|_| DecodeError::InvalidPcOffset(value))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.pos = end;
+
+        Ok(slice)
+    }
+
+    fn read_string(&mut self) -> Result<String, DecodeError> {
+        let len = self.read_leb128_u64()? as usize;
+        let bytes = self.read_bytes(len)?;
+
+        core::str::from_utf8(bytes)
+            .map(str::to_string)
+            .map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    fn read_value(&mut self) -> Result<Value, DecodeError> {
+        match self.read_u8()? {
+            VALUE_NIL => Ok(Value::Nil),
+            VALUE_BOOLEAN => Ok(Value::Boolean(self.read_u8()? != 0)),
+            VALUE_INTEGER => Ok(Value::Integer(self.read_leb128_i64()? as isize)),
+            VALUE_FLOAT => {
+                let bytes = self.read_bytes(8)?;
+
+                Ok(Value::Float(
+                    f64::from_le_bytes(bytes.try_into().unwrap()).into(),
+                ))
+            }
+            VALUE_CHAR => {
+                let codepoint = self.read_leb128_u64()? as u32;
+
+                char::from_u32(codepoint)
+                    .map(Value::Char)
+                    .ok_or(DecodeError::InvalidChar(codepoint))
+            }
+            VALUE_STRING => Ok(Value::String(self.read_string()?)),
+            VALUE_IDENTIFIER => Ok(Value::Identifier(self.read_string()?)),
+            VALUE_CELL => {
+                let l = self.read_value()?;
+                let r = self.read_value()?;
+
+                Ok(Value::Cell(Rc::new(l), Rc::new(r)))
+            }
+            VALUE_QUOTED => Ok(Value::Quoted(Rc::new(self.read_value()?))),
+            VALUE_ERROR => Ok(Value::Error),
+            tag => Err(DecodeError::InvalidValueTag(tag)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::identifier;
+
+    #[test]
+    fn round_trips_all_instruction_kinds() {
+        let instructions = vec![
+            Instruction::AllocRegisters { count: 4 },
+            Instruction::LoadImmediate {
+                dest: 0,
+                value: Value::Integer(-7),
+            },
+            Instruction::LoadImmediate {
+                dest: 1,
+                value: Value::String("abc".to_string()),
+            },
+            Instruction::CallInternal {
+                ident: identifier("+"),
+                base: 0,
+                num_args: 3,
+            },
+            Instruction::CallFunction {
+                target: 12,
+                base: 2,
+                num_args: 2,
+            },
+            Instruction::Return { src: 3 },
+            Instruction::Copy { dest: 1, src: 0 },
+            Instruction::JumpIf {
+                cond: 0,
+                distance: -5,
+            },
+        ];
+
+        assert_eq!(decode(&encode(&instructions)).unwrap(), instructions);
+    }
+
+    #[test]
+    fn round_trips_every_value_variant() {
+        let values = vec![
+            Value::Nil,
+            Value::Boolean(true),
+            Value::Integer(-42),
+            Value::Float(3.5.into()),
+            Value::Char('λ'),
+            Value::String("hi".to_string()),
+            Value::Identifier(identifier("foo")),
+            Value::Cell(Rc::new(Value::Integer(1)), Rc::new(Value::Integer(2))),
+            Value::Quoted(Rc::new(Value::Boolean(false))),
+            Value::Error,
+        ];
+
+        for value in values {
+            let instructions = vec![Instruction::LoadImmediate {
+                dest: 0,
+                value: value.clone(),
+            }];
+
+            assert_eq!(decode(&encode(&instructions)).unwrap(), instructions);
+        }
+    }
+
+    #[test]
+    fn decode_rejects_an_unknown_opcode() {
+        assert_eq!(decode(&[0xff]), Err(DecodeError::InvalidOpcode(0xff)));
+    }
+
+    #[test]
+    fn decode_rejects_a_truncated_stream() {
+        assert_eq!(
+            decode(&[OP_LOAD_IMMEDIATE, 0]),
+            Err(DecodeError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn decode_rejects_an_out_of_range_register_offset() {
+        let mut bytes = vec![OP_ALLOC_REGISTERS];
+        let out_of_range = RegisterOffset::MAX as i64 + 1;
+        write_leb128_i64(out_of_range, &mut bytes);
+
+        assert_eq!(
+            decode(&bytes),
+            Err(DecodeError::InvalidRegisterOffset(out_of_range))
+        );
+    }
+
+    #[cfg(feature = "disasm")]
+    mod disasm_tests {
+        use super::*;
+
+        #[test]
+        fn disassembles_to_the_same_text_the_compiler_snapshots() {
+            let instructions = vec![
+                Instruction::AllocRegisters { count: 3 },
+                Instruction::LoadImmediate {
+                    dest: 0,
+                    value: Value::Integer(1),
+                },
+                Instruction::CallInternal {
+                    ident: identifier("+"),
+                    base: 0,
+                    num_args: 3,
+                },
+                Instruction::CallFunction {
+                    target: 5,
+                    base: 0,
+                    num_args: 2,
+                },
+                Instruction::Return { src: 0 },
+            ];
+
+            assert_eq!(
+                disasm(&encode(&instructions)).unwrap(),
+                vec![
+                    (0, "alloc 3".to_string()),
+                    (1, "load 0 1".to_string()),
+                    (2, "call + 0 3".to_string()),
+                    (3, "callf 5 0 2".to_string()),
+                    (4, "ret 0".to_string()),
+                ]
+            );
+        }
+
+        #[test]
+        fn disasm_reports_the_offset_of_an_invalid_opcode() {
+            let mut bytes = encode(&[Instruction::AllocRegisters { count: 1 }]);
+            bytes.push(0xff);
+
+            assert_eq!(
+                disasm(&bytes),
+                Err(DisasmError::InvalidOpcode {
+                    opcode: 0xff,
+                    offset: bytes.len() - 1,
+                })
+            );
+        }
+    }
+}
@@ -4,21 +4,37 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
 use thiserror::Error;
 
-use crate::value::{Identifier, Value};
+use crate::types::Type;
+use crate::value::{self, Identifier, Value};
 
 pub type RegisterId = u8;
 pub type RegisterOffset = i16;
 
+/// An index into the instruction stream.
+pub type Pc = usize;
+/// A signed jump distance in instructions, added to a [`Pc`] by wrapping, so a negative distance
+/// jumps backward.
+pub type PcOffset = i32;
+
 #[derive(Debug)]
 pub struct Registers {
     values: Vec<Value>,
+    /// Parallel to `values`: whether each register has been written to since it was allocated.
+    /// A freshly `allocate`d cell reads back as `Value::Nil` but starts out `false` here, so
+    /// [`crate::vm::Vm`] can tell "deliberately nil" apart from "never assigned" when its
+    /// uninitialized-read trap (see [`crate::vm::Vm::set_trap_uninitialized_reads`]) is enabled.
+    initialized: Vec<bool>,
     offset_stack: Vec<usize>,
     offset: usize,
 }
 
-impl std::ops::Index<RegisterId> for Registers {
+impl core::ops::Index<RegisterId> for Registers {
     type Output = Value;
 
     fn index(&self, index: u8) -> &Value {
@@ -26,7 +42,7 @@ impl std::ops::Index<RegisterId> for Registers {
     }
 }
 
-impl std::ops::IndexMut<RegisterId> for Registers {
+impl core::ops::IndexMut<RegisterId> for Registers {
     fn index_mut(&mut self, index: u8) -> &mut Value {
         &mut self.values[self.offset + index as usize]
     }
@@ -36,16 +52,50 @@ impl Registers {
     pub fn new() -> Self {
         Self {
             values: vec![],
+            initialized: vec![],
             offset_stack: vec![],
             offset: 0,
         }
     }
 
     pub fn allocate(&mut self, count: RegisterOffset) {
-        self.values.resize_with(
-            (self.values.len() as RegisterOffset + count) as usize,
-            || Value::Nil,
-        );
+        // Widened to `i64` so a `count` near `RegisterOffset::MIN`/`MAX` (reachable from malformed
+        // bytecode, not just a well-behaved compiler) can't overflow the addition; the result is
+        // clamped at zero rather than going negative.
+        let new_len = (self.values.len() as i64 + count as i64).max(0) as usize;
+        self.values.resize_with(new_len, || Value::Nil);
+        self.initialized.resize_with(new_len, || false);
+    }
+
+    /// The total number of registers currently allocated, independent of the active window — used
+    /// to bounds-check a register id before it's used to index into the window.
+    pub fn allocated_len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Like indexing with `[]`, but returns `None` instead of panicking if `index` (relative to
+    /// the active window) falls outside the allocated registers.
+    pub fn get(&self, index: RegisterId) -> Option<&Value> {
+        self.values.get(self.offset + index as usize)
+    }
+
+    /// The fallible counterpart to [`Registers::get`].
+    pub fn get_mut(&mut self, index: RegisterId) -> Option<&mut Value> {
+        self.values.get_mut(self.offset + index as usize)
+    }
+
+    /// Whether register `index` (relative to the active window) has been written to since it was
+    /// allocated, or `None` if `index` falls outside the allocated registers.
+    pub fn is_initialized(&self, index: RegisterId) -> Option<bool> {
+        self.initialized.get(self.offset + index as usize).copied()
+    }
+
+    /// Marks register `index` (relative to the active window) as having been written to. A no-op
+    /// if `index` falls outside the allocated registers.
+    pub fn mark_initialized(&mut self, index: RegisterId) {
+        if let Some(flag) = self.initialized.get_mut(self.offset + index as usize) {
+            *flag = true;
+        }
     }
 
     pub fn push_window(&mut self, size: RegisterOffset) {
@@ -64,10 +114,17 @@ impl Registers {
         self.offset = self.offset_stack.pop().unwrap_or(0);
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, Value> {
+    pub fn iter(&self) -> core::slice::Iter<'_, Value> {
         self.values[self.offset..].iter()
     }
 
+    /// A clone of every value currently visible through the active window, indexed the same way
+    /// [`Registers::get`] is — used by [`crate::vm::Vm`]'s trace mode to diff a register's value
+    /// before and after an instruction runs.
+    pub fn window_snapshot(&self) -> Vec<Value> {
+        self.values[self.offset..].to_vec()
+    }
+
     pub fn into_values(self) -> Vec<Value> {
         self.values
     }
@@ -79,6 +136,26 @@ pub enum InstructionError {
     MissingTentativeField(String),
 }
 
+/// An error raised by a builtin while it's running, surfaced to the embedder through
+/// [`crate::vm::Error`]'s `... (at PC 0x..)` wrapper instead of aborting the process.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{0}")]
+    Value(#[from] value::Error),
+    /// `register` is the argument register (relative to the failing `CallInternal`'s `base`)
+    /// that held the offending value, so the VM can translate it back to a source position.
+    #[error("expected {expected}, got {found}")]
+    TypeMismatch {
+        register: RegisterId,
+        expected: Type,
+        found: Type,
+    },
+    #[error("division by zero")]
+    DivideByZero,
+    #[error("index {index} out of bounds for a list of length {length}")]
+    IndexOutOfBounds { index: isize, length: usize },
+}
+
 macro_rules! instruction_kind {
     (
         ($($upper_state:tt)*)
@@ -161,10 +238,10 @@ macro_rules! instruction_definitions_inner {
                     $($tentative_accum)*
                 }
 
-                impl std::convert::TryInto<Instruction> for TentativeInstruction {
+                impl core::convert::TryInto<Instruction> for TentativeInstruction {
                     type Error = InstructionError;
 
-                    fn try_into(self) -> std::result::Result<Instruction, InstructionError> {
+                    fn try_into(self) -> core::result::Result<Instruction, InstructionError> {
                         match self {
                             $($try_into_accum)*
                         }
@@ -191,6 +268,17 @@ instruction_definitions! {
         dest: RegisterId,
         value: Value,
     },
+    // Copy `src` into `dest`.
+    Copy {
+        dest: RegisterId,
+        src: RegisterId,
+    },
+    // If `cond` holds `true`, jump forward (or backward, for a negative distance) by `distance`
+    // instructions.
+    JumpIf {
+        cond: RegisterId,
+        distance: PcOffset,
+    },
     // Call the given function, passing the last `num_args` registers as the registers visible to
     // the function.
     CallInternal {
@@ -198,20 +286,40 @@ instruction_definitions! {
         base: RegisterId,
         num_args: RegisterOffset,
     },
+    // Push the current PC and a register window starting at `base`, then jump to `target`, which
+    // must be the start of a user-defined function's compiled body.
+    CallFunction {
+        target: Pc,
+        base: RegisterId,
+        num_args: RegisterOffset,
+    },
+    // Copy `src` into register 0 of the current window, pop it, and jump back to the PC pushed by
+    // the matching `CallFunction`.
+    Return {
+        src: RegisterId,
+    },
 }
 
-impl std::fmt::Display for Instruction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         use Instruction::*;
 
         match self {
             AllocRegisters { count } => write!(f, "alloc {}", count),
             LoadImmediate { dest, value } => write!(f, "load {} {}", dest, value),
+            Copy { dest, src } => write!(f, "copy {} {}", dest, src),
+            JumpIf { cond, distance } => write!(f, "jump_if {} {} ", cond, distance),
             CallInternal {
                 ident,
                 base,
                 num_args,
             } => write!(f, "call {} {} {}", ident, base, num_args),
+            CallFunction {
+                target,
+                base,
+                num_args,
+            } => write!(f, "callf {} {} {}", target, base, num_args),
+            Return { src } => write!(f, "ret {}", src),
         }
     }
 }
@@ -241,6 +349,30 @@ macro_rules! instructions_inner {
             $($rest)*
         )
     };
+    ( ($($accum:tt)*) copy $dest:tt $src:tt; $($rest:tt)* ) => {
+        crate::instructions_inner!(
+            (
+                $($accum)*
+                $crate::vm::code::Instruction::Copy {
+                    dest: $dest,
+                    src: $src,
+                },
+            )
+            $($rest)*
+        )
+    };
+    ( ($($accum:tt)*) jump_if $cond:tt $distance:expr; $($rest:tt)* ) => {
+        crate::instructions_inner!(
+            (
+                $($accum)*
+                $crate::vm::code::Instruction::JumpIf {
+                    cond: $cond,
+                    distance: $distance,
+                },
+            )
+            $($rest)*
+        )
+    };
     ( ($($accum:tt)*) call $ident:tt $base:tt $num_args:expr; $($rest:tt)* ) => {
         crate::instructions_inner!(
             (
@@ -254,8 +386,32 @@ macro_rules! instructions_inner {
             $($rest)*
         )
     };
+    ( ($($accum:tt)*) callf $target:tt $base:tt $num_args:expr; $($rest:tt)* ) => {
+        crate::instructions_inner!(
+            (
+                $($accum)*
+                $crate::vm::code::Instruction::CallFunction {
+                    target: $target,
+                    base: $base,
+                    num_args: $num_args,
+                },
+            )
+            $($rest)*
+        )
+    };
+    ( ($($accum:tt)*) ret $src:tt; $($rest:tt)* ) => {
+        crate::instructions_inner!(
+            (
+                $($accum)*
+                $crate::vm::code::Instruction::Return {
+                    src: $src,
+                },
+            )
+            $($rest)*
+        )
+    };
     ( ($($accum:tt)*) ) => {
-        vec![$($accum)*]
+        $crate::vec![$($accum)*]
     };
 }
 
@@ -4,34 +4,59 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+pub mod bytecode;
 pub mod code;
+pub mod optimize;
 
-use std::io;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+#[cfg(not(feature = "std"))]
+use hashbrown::HashMap;
+use result_at::Span;
 use thiserror::Error;
 
 use crate::builtins;
 use crate::value::{self, Value};
 
-type Result<T> = std::result::Result<T, Error>;
-type IResult<T> = std::result::Result<T, ErrorInternal>;
+type Result<T> = core::result::Result<T, Error>;
+type IResult<T> = core::result::Result<T, ErrorInternal>;
+
+/// The source span of each argument register a `CallInternal` passed to its builtin, keyed by
+/// the `Pc` of that `CallInternal`, so a runtime `TypeMismatch` can be traced back to the
+/// expression that produced the offending value.
+pub type ArgumentPositions = HashMap<code::Pc, Vec<Span>>;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct Error {
     error: ErrorInternal,
     pc: code::Pc,
+    position: Option<(usize, usize)>,
 }
 
 impl Error {
-    fn from_internal(error: ErrorInternal, pc: code::Pc) -> Self {
-        Error { error, pc }
+    fn from_internal(error: ErrorInternal, pc: code::Pc, position: Option<(usize, usize)>) -> Self {
+        Error {
+            error,
+            pc,
+            position,
+        }
     }
 }
 
-impl std::error::Error for Error {}
+impl core::error::Error for Error {}
 
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "{} (at PC 0x{:x})", self.error, self.pc)
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::result::Result<(), core::fmt::Error> {
+        match self.position {
+            Some((line, column)) => write!(f, "{}:{}: {}", line, column, self.error),
+            None => write!(f, "{} (at PC 0x{:x})", self.error, self.pc),
+        }
     }
 }
 
@@ -44,65 +69,237 @@ enum ErrorInternal {
         #[from]
         source: code::Error,
     },
+    #[error("execution fuel exhausted after {cycles} cycles")]
+    Exhausted { cycles: u64 },
+    #[error("returned with no matching call")]
+    ReturnWithoutCall,
+    #[error("jump target {target} is out of bounds (program has {len} instructions)")]
+    InvalidJumpTarget { target: code::Pc, len: usize },
+    #[error("register {register} is out of bounds ({len} registers allocated)")]
+    InvalidRegister { register: code::RegisterId, len: usize },
+    #[error("register {id} was read before ever being written")]
+    UninitializedRegister { id: code::RegisterId },
     #[error("placeholder")]
     Placeholder,
 }
 
 pub struct Vm<'a> {
     instructions: Vec<code::Instruction>,
+    argument_positions: ArgumentPositions,
+    /// The `BuiltinFn` a `CallInternal` at a given `Pc` resolved to the first time it ran, so
+    /// later dispatches of that same call site skip straight past `builtins::get`'s hash lookup.
+    /// Parallel to `instructions`, and reset whenever a new program is loaded.
+    call_cache: Vec<Option<&'static builtins::BuiltinFn>>,
     pub(crate) registers: code::Registers,
-    pub(crate) debug_output: &'a mut dyn io::Write,
+    pub(crate) debug_output: &'a mut dyn core::fmt::Write,
+    cycles: u64,
+    fuel: Option<u64>,
+    /// Whether [`Vm::run`] should emit a structured trace line to `debug_output` for every
+    /// instruction it executes — see [`Vm::set_trace`].
+    trace: bool,
+    /// Whether reading a register that was allocated but never written should raise
+    /// `ErrorInternal::UninitializedRegister` instead of silently returning `Value::Nil` — see
+    /// [`Vm::set_trap_uninitialized_reads`].
+    trap_uninitialized_reads: bool,
+    call_stack: Vec<code::Pc>,
 }
 
 impl<'a> Vm<'a> {
-    pub fn new(debug_output: &'a mut impl io::Write) -> Self {
+    pub fn new(debug_output: &'a mut impl core::fmt::Write) -> Self {
         Self {
-            instructions: vec![],
+            instructions: Vec::new(),
+            argument_positions: ArgumentPositions::new(),
+            call_cache: Vec::new(),
             registers: code::Registers::new(),
             debug_output,
+            cycles: 0,
+            fuel: None,
+            trace: false,
+            trap_uninitialized_reads: false,
+            call_stack: Vec::new(),
         }
     }
 
+    /// Builds a `Vm` that halts with `Error::Exhausted` once it has dispatched `fuel`
+    /// instructions, rather than running forever.
+    pub fn with_fuel(debug_output: &'a mut impl core::fmt::Write, fuel: u64) -> Self {
+        let mut vm = Self::new(debug_output);
+        vm.set_fuel(Some(fuel));
+
+        vm
+    }
+
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// Builds a `Vm` with tracing enabled from the start — see [`Vm::set_trace`].
+    pub fn with_trace(debug_output: &'a mut impl core::fmt::Write) -> Self {
+        let mut vm = Self::new(debug_output);
+        vm.set_trace(true);
+
+        vm
+    }
+
+    /// When enabled, `run` writes one line to `debug_output` per instruction it dispatches: its
+    /// `Pc`, its decoded form, and any window register whose value changed, plus a pair of lines
+    /// around a `CallInternal` marking the window it pushed for the call and popped afterward.
+    /// Disabled by default, and gated behind a single check in `run`'s loop so leaving it off
+    /// costs nothing beyond that check.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    /// Builds a `Vm` that traps uninitialized-register reads from the start — see
+    /// [`Vm::set_trap_uninitialized_reads`].
+    pub fn with_trap_uninitialized_reads(debug_output: &'a mut impl core::fmt::Write) -> Self {
+        let mut vm = Self::new(debug_output);
+        vm.set_trap_uninitialized_reads(true);
+
+        vm
+    }
+
+    /// When enabled, reading a register that was `AllocRegisters`-ed but never since written —
+    /// whether as a `Copy` source, a `JumpIf` condition, a `Return` value, or a `CallInternal`
+    /// argument — raises `ErrorInternal::UninitializedRegister` instead of the default behavior
+    /// of silently treating it as `Value::Nil`. Disabled by default, to preserve that default
+    /// behavior for existing callers that rely on it.
+    pub fn set_trap_uninitialized_reads(&mut self, trap: bool) {
+        self.trap_uninitialized_reads = trap;
+    }
+
+    /// The number of instructions dispatched so far across all calls to [`Vm::run`].
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
     pub fn load(&mut self, instructions: Vec<code::Instruction>) {
+        self.call_cache = vec![None; instructions.len()];
         self.instructions = instructions;
     }
 
+    /// Like [`Vm::load`], but additionally records the source span of each `CallInternal`
+    /// argument so a runtime `TypeMismatch` can be reported at the position of the offending
+    /// expression instead of only the failing instruction's `Pc`.
+    pub fn load_with_positions(
+        &mut self,
+        instructions: Vec<code::Instruction>,
+        argument_positions: ArgumentPositions,
+    ) {
+        self.call_cache = vec![None; instructions.len()];
+        self.instructions = instructions;
+        self.argument_positions = argument_positions;
+    }
+
     pub fn run(&mut self) -> Result<()> {
         use code::Instruction::*;
 
         let mut pc = 0;
         while pc < self.instructions.len() {
+            if let Some(fuel) = self.fuel {
+                if self.cycles >= fuel {
+                    return Err(Error::from_internal(
+                        ErrorInternal::Exhausted {
+                            cycles: self.cycles,
+                        },
+                        pc,
+                        None,
+                    ));
+                }
+            }
+
             let instruction = self.instructions[pc].clone();
             let cur_pc = pc;
             pc += 1;
+            self.cycles = self.cycles.wrapping_add(1);
+
+            let trace_before = self
+                .trace
+                .then(|| (format!("{}", instruction), self.registers.window_snapshot()));
 
-            if let Err(e) = match instruction {
+            let result = match instruction {
                 AllocRegisters { count } => {
                     self.registers.allocate(count);
                     Ok(())
                 }
-                LoadImmediate { dest, value } => {
-                    self.registers[dest] = value;
-                    Ok(())
-                }
-                Copy { dest, src } => {
-                    self.registers[dest] = self.registers[src].clone();
-                    Ok(())
-                }
+                LoadImmediate { dest, value } => self.write_register(dest, value),
+                Copy { dest, src } => self
+                    .read_register(src)
+                    .and_then(|v| self.write_register(dest, v)),
                 CallInternal {
                     ident,
                     base,
                     num_args,
-                } => self.call_internal(ident, base, num_args),
-                JumpIf { cond, distance } => {
-                    if self.registers[cond] == Value::Boolean(true) {
-                        pc = pc.wrapping_add(distance as code::Pc);
+                } => self.call_internal(cur_pc, ident, base, num_args),
+                JumpIf { cond, distance } => match self.read_register(cond) {
+                    Ok(Value::Boolean(true)) => {
+                        let target = pc.wrapping_add(distance as code::Pc);
+
+                        if target > self.instructions.len() {
+                            Err(ErrorInternal::InvalidJumpTarget {
+                                target,
+                                len: self.instructions.len(),
+                            })
+                        } else {
+                            pc = target;
+
+                            Ok(())
+                        }
+                    }
+                    Ok(_) => Ok(()),
+                    Err(e) => Err(e),
+                },
+                CallFunction {
+                    target,
+                    base,
+                    num_args,
+                } => {
+                    if target >= self.instructions.len() {
+                        Err(ErrorInternal::InvalidJumpTarget {
+                            target,
+                            len: self.instructions.len(),
+                        })
+                    } else if let Err(e) = self.check_window(base, num_args) {
+                        Err(e)
+                    } else {
+                        self.call_stack.push(pc);
+                        self.registers.push_window_starting(base);
+                        pc = target;
+
+                        Ok(())
                     }
-
-                    Ok(())
                 }
-            } {
-                return Err(Error::from_internal(e, cur_pc));
+                Return { src } => self.read_register(src).and_then(|v| {
+                    self.write_register(0, v)?;
+                    self.registers.pop_window();
+
+                    match self.call_stack.pop() {
+                        Some(return_pc) => {
+                            pc = return_pc;
+                            Ok(())
+                        }
+                        None => Err(ErrorInternal::ReturnWithoutCall),
+                    }
+                }),
+            };
+
+            if let Some((instruction_text, before)) = trace_before {
+                self.emit_trace(cur_pc, &instruction_text, &before);
+            }
+
+            if let Err(e) = result {
+                let position = match &e {
+                    ErrorInternal::Runtime {
+                        source: code::Error::TypeMismatch { register, .. },
+                    } => self
+                        .argument_positions
+                        .get(&cur_pc)
+                        .and_then(|spans| spans.get(*register as usize))
+                        .map(|span| span.start),
+                    _ => None,
+                };
+
+                return Err(Error::from_internal(e, cur_pc, position));
             }
         }
 
@@ -111,24 +308,135 @@ impl<'a> Vm<'a> {
 
     fn call_internal(
         &mut self,
+        cur_pc: code::Pc,
         ident: value::Identifier,
         base: code::RegisterId,
         num_args: code::RegisterOffset,
     ) -> IResult<()> {
+        let func = match self.call_cache[cur_pc] {
+            Some(func) => func,
+            None => {
+                let func = builtins::get(&ident)
+                    .ok_or(ErrorInternal::UnknownInternalFunction(ident.clone()))?
+                    .run;
+                self.call_cache[cur_pc] = Some(func);
+
+                func
+            }
+        };
+
+        self.check_window(base, num_args)?;
+
+        if self.trace {
+            write!(self.debug_output, "  window: push @{} ({})\n", base, ident).unwrap();
+        }
+
         self.registers.push_window_starting(base);
 
-        (builtins::get(&ident)
-            .ok_or(ErrorInternal::UnknownInternalFunction(ident.clone()))?
-            .run)(self, num_args)?;
+        if self.trap_uninitialized_reads {
+            for offset in 0..num_args.max(0) as usize {
+                let id = offset as code::RegisterId;
+
+                if self.registers.is_initialized(id) == Some(false) {
+                    return Err(ErrorInternal::UninitializedRegister { id });
+                }
+            }
+        }
+
+        func(self, num_args)?;
+
+        // A builtin conventionally writes its result to register 0 of the window regardless of
+        // `num_args` (see `check_window`'s doc comment), so the whole window it was handed counts
+        // as written now; `__match`, which also writes its bindings into the registers past its
+        // two arguments, marks those itself.
+        // Marked while the window is still active, since `mark_initialized` takes a
+        // window-relative id.
+        for offset in 0..num_args.max(1) as usize {
+            self.registers.mark_initialized(offset as code::RegisterId);
+        }
 
         self.registers.pop_window();
 
+        if self.trace {
+            write!(self.debug_output, "  window: pop\n").unwrap();
+        }
+
         Ok(())
     }
 
+    /// Writes one trace line for the instruction at `pc` (see [`Vm::set_trace`]): its decoded
+    /// form, followed by `r<id>: <old> -> <new>` for every window register `before` doesn't match
+    /// the current, post-execution value of.
+    fn emit_trace(&mut self, pc: code::Pc, instruction: &str, before: &[Value]) {
+        let after = self.registers.window_snapshot();
+        let mut changes = String::new();
+
+        for (id, old) in before.iter().enumerate() {
+            if let Some(new) = after.get(id) {
+                if new != old {
+                    changes.push_str(&format!(" r{}: {} -> {}", id, old, new));
+                }
+            }
+        }
+
+        write!(self.debug_output, "0x{:x}: {}{}\n", pc, instruction, changes).unwrap();
+    }
+
+    /// Checks that `base..base + num_args` (and at least `base` itself, since nearly every
+    /// builtin writes its result to register 0 of the window regardless of `num_args`) falls
+    /// within the registers actually allocated, so `CallInternal`/`CallFunction` can't hand a
+    /// builtin or function body a window that reads or writes past the end of `Registers`.
+    fn check_window(&self, base: code::RegisterId, num_args: code::RegisterOffset) -> IResult<()> {
+        let required = num_args.max(1) as usize;
+
+        if base as usize + required > self.registers.allocated_len() {
+            Err(ErrorInternal::InvalidRegister {
+                register: base,
+                len: self.registers.allocated_len(),
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    fn read_register(&self, id: code::RegisterId) -> IResult<Value> {
+        if self.trap_uninitialized_reads && self.registers.is_initialized(id) == Some(false) {
+            return Err(ErrorInternal::UninitializedRegister { id });
+        }
+
+        self.registers
+            .get(id)
+            .cloned()
+            .ok_or(ErrorInternal::InvalidRegister {
+                register: id,
+                len: self.registers.allocated_len(),
+            })
+    }
+
+    fn write_register(&mut self, id: code::RegisterId, value: Value) -> IResult<()> {
+        match self.registers.get_mut(id) {
+            Some(slot) => {
+                *slot = value;
+                self.registers.mark_initialized(id);
+                Ok(())
+            }
+            None => Err(ErrorInternal::InvalidRegister {
+                register: id,
+                len: self.registers.allocated_len(),
+            }),
+        }
+    }
+
     fn into_registers(self) -> Vec<Value> {
         self.registers.into_values()
     }
+
+    /// Reads the current value of register `id` without consuming the `Vm`, so a caller that
+    /// keeps reusing one `Vm` across multiple `load`/`run` cycles (see
+    /// [`crate::compiler::Session`]) can retrieve a line's result value in between runs.
+    pub fn register(&self, id: code::RegisterId) -> &Value {
+        &self.registers[id]
+    }
 }
 
 #[cfg(test)]
@@ -140,7 +448,7 @@ mod tests {
     use k9::{assert_err_matches_regex, snapshot};
 
     fn run_into_registers(instructions: Vec<code::Instruction>) -> Result<Vec<Value>> {
-        let mut debug_output = Vec::new();
+        let mut debug_output = String::new();
         let registers = {
             let mut vm = Vm::new(&mut debug_output);
             vm.load(instructions);
@@ -148,22 +456,44 @@ mod tests {
             Ok(vm.into_registers())
         }?;
 
-        if debug_output.len() != 0 {
-            dbg!(std::str::from_utf8(&debug_output).unwrap());
+        if !debug_output.is_empty() {
+            dbg!(&debug_output);
         }
 
         Ok(registers)
     }
 
+    fn run_with_trap_uninitialized_reads(
+        instructions: Vec<code::Instruction>,
+    ) -> Result<Vec<Value>> {
+        let mut debug_output = String::new();
+        let mut vm = Vm::with_trap_uninitialized_reads(&mut debug_output);
+        vm.load(instructions);
+        vm.run()?;
+
+        Ok(vm.into_registers())
+    }
+
     fn run_into_output(instructions: Vec<code::Instruction>) -> Result<String> {
-        let mut debug_output = Vec::new();
+        let mut debug_output = String::new();
         {
             let mut vm = Vm::new(&mut debug_output);
             vm.load(instructions);
             vm.run()?;
         }
 
-        Ok(String::from_utf8(debug_output).unwrap())
+        Ok(debug_output)
+    }
+
+    fn run_with_trace_into_output(instructions: Vec<code::Instruction>) -> Result<String> {
+        let mut debug_output = String::new();
+        {
+            let mut vm = Vm::with_trace(&mut debug_output);
+            vm.load(instructions);
+            vm.run()?;
+        }
+
+        Ok(debug_output)
     }
 
     #[test]
@@ -271,6 +601,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn fuel_exhausted() -> Result<()> {
+        let mut debug_output = Vec::new();
+        let mut vm = Vm::with_fuel(&mut debug_output, 2);
+        vm.load(instructions! {
+            alloc 1;
+            load 0 1;
+            load 0 2;
+        });
+
+        assert_err_matches_regex!(vm.run(), "Exhausted");
+
+        Ok(())
+    }
+
+    #[test]
+    fn cycles_counts_dispatched_instructions() -> Result<()> {
+        let mut debug_output = Vec::new();
+        let mut vm = Vm::new(&mut debug_output);
+        vm.load(instructions! {
+            alloc 1;
+            load 0 1;
+        });
+        vm.run()?;
+
+        assert_eq!(vm.cycles(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_function_basic() -> Result<()> {
+        snapshot!(
+            run_into_registers(instructions! {
+                alloc 3;
+                load 0 3;
+                load 1 4;
+                callf 6 0 2;
+                load 2 true;
+                jump_if 2 2;
+                call + 0 2;
+                ret 0;
+                load 2 99;
+            })?,
+            "
+[
+    Integer(
+        7,
+    ),
+    Integer(
+        4,
+    ),
+    Integer(
+        99,
+    ),
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn return_without_call_fails() -> Result<()> {
+        assert_err_matches_regex!(
+            run_into_registers(instructions! {
+                alloc 1;
+                ret 0;
+            }),
+            "ReturnWithoutCall"
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn debug() -> Result<()> {
         snapshot!(
@@ -284,6 +689,29 @@ mod tests {
             r#"
 "blah" 100 (abc)
 
+"#
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn trace() -> Result<()> {
+        snapshot!(
+            run_with_trace_into_output(instructions! {
+                alloc 2;
+                load 0 42;
+                load 1 93;
+                call + 0 2;
+            })?,
+            r#"
+0x0: alloc 2
+0x1: load 0 42 r0: nil -> 42
+0x2: load 1 93 r1: nil -> 93
+  window: push @0 (+)
+  window: pop
+0x3: call + 0 2 r0: 42 -> 135
+
 "#
         );
 
@@ -319,6 +747,71 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn jump_if_basic_leaves_register_uninitialized_by_default() -> Result<()> {
+        // Mirrors `jump_if_basic`: register 1's `load` is skipped by the jump, so it's read back
+        // as `Nil` rather than trapped, since `trap_uninitialized_reads` defaults to off.
+        snapshot!(
+            run_into_registers(instructions! {
+                alloc 2;
+                load 0 true;
+                jump_if 0 1;
+                load 1 1;
+
+                copy 1 1;
+            })?,
+            "
+[
+    Boolean(
+        true,
+    ),
+    Nil,
+]
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn uninitialized_register_read_is_trapped_in_strict_mode() -> Result<()> {
+        assert_err_matches_regex!(
+            run_with_trap_uninitialized_reads(instructions! {
+                alloc 2;
+                load 0 true;
+                jump_if 0 1;
+                load 1 1;
+
+                copy 1 1;
+            }),
+            "UninitializedRegister"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn type_mismatch_includes_source_position() -> Result<()> {
+        let mut debug_output = String::new();
+        let mut vm = Vm::new(&mut debug_output);
+
+        let mut argument_positions = ArgumentPositions::new();
+        argument_positions.insert(3, vec![Span::point((3, 32)), Span::point((3, 37))]);
+        vm.load_with_positions(
+            instructions! {
+                alloc 2;
+                load 0 true;
+                load 1 1;
+                call + 0 2;
+            },
+            argument_positions,
+        );
+
+        assert_eq!(format!("{}", vm.run().unwrap_err()), "3:32: expected integer, got boolean");
+
+        Ok(())
+    }
+
     #[test]
     fn jump_if_loop() -> Result<()> {
         snapshot!(
@@ -352,4 +845,130 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn out_of_range_register_fails() -> Result<()> {
+        assert_err_matches_regex!(
+            run_into_registers(instructions! {
+                alloc 1;
+                copy 0 200;
+            }),
+            "InvalidRegister"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn call_internal_window_past_allocated_registers_fails() -> Result<()> {
+        assert_err_matches_regex!(
+            run_into_registers(instructions! {
+                alloc 1;
+                call + 200 2;
+            }),
+            "InvalidRegister"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn jump_if_out_of_range_cond_fails() -> Result<()> {
+        assert_err_matches_regex!(
+            run_into_registers(instructions! {
+                alloc 1;
+                jump_if 200 1;
+            }),
+            "InvalidRegister"
+        );
+
+        Ok(())
+    }
+
+    /// A tiny deterministic xorshift PRNG, used only to generate pseudo-random bytecode for
+    /// [`run_never_panics_on_malformed_bytecode`] below — pulling in a real fuzzing dependency
+    /// for one test isn't worth it when all that's needed is "varied, reproducible" input.
+    struct XorShift64(u64);
+
+    impl XorShift64 {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+
+            self.0
+        }
+
+        fn next_u8(&mut self) -> u8 {
+            self.next() as u8
+        }
+
+        fn next_i16(&mut self) -> i16 {
+            self.next() as i16
+        }
+
+        fn next_i32(&mut self) -> i32 {
+            self.next() as i32
+        }
+
+        fn choose<'a, T>(&mut self, options: &'a [T]) -> &'a T {
+            &options[self.next() as usize % options.len()]
+        }
+    }
+
+    /// Only identifiers naming a public builtin whose register reads/writes stay within its
+    /// declared `num_args` — the double-underscore internal builtins (`__match`, `__cons`,
+    /// `__append`) trust the compiler to have reserved extra registers beyond `num_args` for
+    /// bindings, an invariant a random instruction stream doesn't (and isn't meant to) uphold.
+    const FUZZ_IDENTS: &[&str] = &["+", "-", "<", "debug", "cons", "car", "cdr", "nth", "unknown"];
+
+    fn random_instruction(rng: &mut XorShift64) -> I {
+        match rng.next() % 7 {
+            0 => I::AllocRegisters {
+                count: rng.next_i16(),
+            },
+            1 => I::LoadImmediate {
+                dest: rng.next_u8(),
+                value: Value::Integer(rng.next() as isize),
+            },
+            2 => I::Copy {
+                dest: rng.next_u8(),
+                src: rng.next_u8(),
+            },
+            3 => I::JumpIf {
+                cond: rng.next_u8(),
+                distance: rng.next_i32(),
+            },
+            4 => I::CallInternal {
+                ident: value::identifier(rng.choose(FUZZ_IDENTS)),
+                base: rng.next_u8(),
+                num_args: rng.next_i16().rem_euclid(4),
+            },
+            5 => I::CallFunction {
+                target: rng.next() as usize % 16,
+                base: rng.next_u8(),
+                num_args: rng.next_i16().rem_euclid(4),
+            },
+            _ => I::Return { src: rng.next_u8() },
+        }
+    }
+
+    #[test]
+    fn run_never_panics_on_malformed_bytecode() {
+        // There's no assertion beyond "doesn't panic": `run` returning any `Result` at all — `Ok`
+        // or a structured `Err` — means the bounds checks on registers, jump targets and the
+        // `CallInternal`/`CallFunction` windows held up against input no real compiler would
+        // emit. A step-bounded `fuel` stands in for the "never infinite-loops" half of that.
+        let mut rng = XorShift64(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            let len = 1 + (rng.next() as usize % 16);
+            let instructions: Vec<I> = (0..len).map(|_| random_instruction(&mut rng)).collect();
+
+            let mut debug_output = String::new();
+            let mut vm = Vm::with_fuel(&mut debug_output, 256);
+            vm.load(instructions);
+            let _ = vm.run();
+        }
+    }
 }
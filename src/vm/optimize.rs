@@ -0,0 +1,391 @@
+// Copyright (c) Jesse Weaver, 2021
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A constant-folding peephole pass over a compiled instruction stream, run ahead of time as an
+//! explicit, opt-in step (see [`optimize`]) rather than something [`crate::vm::Vm::load`] does
+//! implicitly, so a caller that doesn't want it pays nothing for it and existing cycle-count-based
+//! tests keep seeing the unoptimized stream.
+//!
+//! The pass walks the instructions once, tracking which registers currently hold a
+//! statically-known [`Value`] (set by `LoadImmediate`, propagated by `Copy`, forgotten by anything
+//! it can't model). A `LoadImmediate`/`Copy` whose value is still only known, and not yet
+//! re-emitted, is left "pending"; it's only written out once something actually needs the real
+//! register to hold it, and dropped entirely if a later write to the same register supersedes it
+//! first. When a `CallInternal` for a builtin tagged [`crate::builtins::Builtin::pure`] has a
+//! fully-constant argument window, it's run once in a throwaway [`Vm`] and replaced with a
+//! `LoadImmediate` of the result, rather than re-executed on every call.
+//!
+//! A jump target is a hard boundary: more than one predecessor can reach it with different
+//! register contents, so everything pending is materialized and everything known is forgotten
+//! right before it, rather than trying to merge dataflow across the incoming edges. The same goes
+//! for `AllocRegisters`, `CallFunction` and `Return`, which all change what the current register
+//! window even means. This keeps the pass simple and always safe at the cost of folding only
+//! within a single straight-line run of instructions, never across a branch or a call.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::builtins;
+use crate::value::{self, Value};
+use crate::vm::code::{self, Instruction, RegisterId, RegisterOffset};
+use crate::vm::Vm;
+
+/// Runs the constant-folding peephole pass described in the module documentation over
+/// `instructions`, returning a new, equivalent instruction stream that's never longer, and is
+/// often shorter.
+pub fn optimize(instructions: Vec<Instruction>) -> Vec<Instruction> {
+    let original_len = instructions.len();
+    let mut is_jump_target = vec![false; original_len + 1];
+
+    for (i, instruction) in instructions.iter().enumerate() {
+        match instruction {
+            Instruction::JumpIf { distance, .. } => {
+                // Mirrors `Vm::run`, which advances `pc` past this instruction before adding
+                // `distance` to it.
+                let target = (i + 1).wrapping_add(*distance as usize);
+
+                if target <= original_len {
+                    is_jump_target[target] = true;
+                }
+            }
+            Instruction::CallFunction { target, .. } if *target <= original_len => {
+                is_jump_target[*target] = true;
+            }
+            _ => {}
+        }
+    }
+
+    let mut known: BTreeMap<RegisterId, Value> = BTreeMap::new();
+    let mut pending: BTreeMap<RegisterId, Value> = BTreeMap::new();
+    let mut output: Vec<Instruction> = Vec::new();
+    // Parallel to `output`: the original index each emitted instruction was produced while
+    // processing, used below to re-derive `JumpIf`/`CallFunction` targets in terms of `output`.
+    let mut origin: Vec<usize> = Vec::new();
+    let mut old_to_new: Vec<usize> = vec![0; original_len + 1];
+
+    for (i, instruction) in instructions.into_iter().enumerate() {
+        if is_jump_target[i] {
+            flush_all(&mut pending, &mut output, &mut origin, i);
+            known.clear();
+        }
+
+        old_to_new[i] = output.len();
+
+        match instruction {
+            Instruction::AllocRegisters { count } => {
+                flush_all(&mut pending, &mut output, &mut origin, i);
+                known.clear();
+
+                output.push(Instruction::AllocRegisters { count });
+                origin.push(i);
+            }
+            Instruction::LoadImmediate { dest, value } => {
+                known.insert(dest, value.clone());
+                pending.insert(dest, value);
+            }
+            Instruction::Copy { dest, src } => match known.get(&src).cloned() {
+                Some(value) => {
+                    known.insert(dest, value.clone());
+                    pending.insert(dest, value);
+                }
+                None => {
+                    known.remove(&dest);
+                    pending.remove(&dest);
+
+                    output.push(Instruction::Copy { dest, src });
+                    origin.push(i);
+                }
+            },
+            Instruction::JumpIf { cond, distance } => {
+                // Everything pending is materialized before the jump, not only `cond`: a taken
+                // jump lands past whatever is flushed at its target for the fall-through path.
+                flush_all(&mut pending, &mut output, &mut origin, i);
+
+                output.push(Instruction::JumpIf { cond, distance });
+                origin.push(i);
+            }
+            Instruction::CallInternal {
+                ident,
+                base,
+                num_args,
+            } => match try_fold(&ident, base, num_args, &known) {
+                Some(value) => {
+                    known.insert(base, value.clone());
+                    pending.insert(base, value);
+                }
+                None => {
+                    for offset in 0..num_args.max(0) as usize {
+                        if let Some(reg) = base.checked_add(offset as RegisterId) {
+                            flush_register(reg, &mut pending, &mut output, &mut origin, i);
+                        }
+                    }
+
+                    known.remove(&base);
+                    pending.remove(&base);
+
+                    output.push(Instruction::CallInternal {
+                        ident,
+                        base,
+                        num_args,
+                    });
+                    origin.push(i);
+                }
+            },
+            Instruction::CallFunction {
+                target,
+                base,
+                num_args,
+            } => {
+                flush_all(&mut pending, &mut output, &mut origin, i);
+                known.clear();
+
+                output.push(Instruction::CallFunction {
+                    target,
+                    base,
+                    num_args,
+                });
+                origin.push(i);
+            }
+            Instruction::Return { src } => {
+                // The final register dump a caller sees (e.g. `Vm::into_registers`) exposes every
+                // physical register regardless of which window is active, so nothing pending can
+                // be left un-materialized across a window pop.
+                flush_all(&mut pending, &mut output, &mut origin, i);
+                known.clear();
+
+                output.push(Instruction::Return { src });
+                origin.push(i);
+            }
+        }
+    }
+
+    // Whatever's still pending at the end of the program is part of the final, externally
+    // observable register state, so it has to be materialized too.
+    flush_all(&mut pending, &mut output, &mut origin, original_len);
+    old_to_new[original_len] = output.len();
+
+    let output_len = output.len();
+    for (new_pc, instruction) in output.iter_mut().enumerate() {
+        match instruction {
+            Instruction::JumpIf { distance, .. } => {
+                let orig_target = (origin[new_pc] + 1).wrapping_add(*distance as usize);
+                let new_target = remap(orig_target, original_len, output_len, &old_to_new);
+
+                *distance = (new_target as i64 - (new_pc + 1) as i64) as code::PcOffset;
+            }
+            Instruction::CallFunction { target, .. } => {
+                *target = remap(*target, original_len, output_len, &old_to_new);
+            }
+            _ => {}
+        }
+    }
+
+    output
+}
+
+/// Translates an original `Pc` into its position in the optimized stream. An already-invalid
+/// original target (out of bounds before optimizing too) is kept out of bounds, rather than
+/// accidentally landing somewhere valid.
+fn remap(
+    original_target: usize,
+    original_len: usize,
+    output_len: usize,
+    old_to_new: &[usize],
+) -> usize {
+    if original_target <= original_len {
+        old_to_new[original_target]
+    } else {
+        output_len + 1
+    }
+}
+
+fn flush_register(
+    id: RegisterId,
+    pending: &mut BTreeMap<RegisterId, Value>,
+    output: &mut Vec<Instruction>,
+    origin: &mut Vec<usize>,
+    current_index: usize,
+) {
+    if let Some(value) = pending.remove(&id) {
+        output.push(Instruction::LoadImmediate { dest: id, value });
+        origin.push(current_index);
+    }
+}
+
+fn flush_all(
+    pending: &mut BTreeMap<RegisterId, Value>,
+    output: &mut Vec<Instruction>,
+    origin: &mut Vec<usize>,
+    current_index: usize,
+) {
+    for (id, value) in core::mem::take(pending) {
+        output.push(Instruction::LoadImmediate { dest: id, value });
+        origin.push(current_index);
+    }
+}
+
+/// If `ident` names a pure builtin and every register in its argument window is statically known,
+/// runs it in a throwaway `Vm` and returns the result it wrote to register 0.
+fn try_fold(
+    ident: &value::Identifier,
+    base: RegisterId,
+    num_args: RegisterOffset,
+    known: &BTreeMap<RegisterId, Value>,
+) -> Option<Value> {
+    let builtin = builtins::get(ident)?;
+    if !builtin.pure {
+        return None;
+    }
+
+    let count = num_args.max(0) as usize;
+    let mut args = Vec::with_capacity(count);
+    for offset in 0..count {
+        let reg = base.checked_add(offset as RegisterId)?;
+        args.push(known.get(&reg)?.clone());
+    }
+
+    let mut debug_output = String::new();
+    let mut vm = Vm::new(&mut debug_output);
+    vm.registers.allocate(args.len().max(1) as RegisterOffset);
+
+    for (i, arg) in args.into_iter().enumerate() {
+        vm.registers[i as RegisterId] = arg;
+    }
+
+    (builtin.run)(&mut vm, num_args).ok()?;
+
+    Some(vm.registers[0].clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instructions;
+    use crate::vm::code::Instruction as I;
+
+    fn run_into_registers(instructions: Vec<Instruction>) -> Vec<Value> {
+        let mut debug_output = String::new();
+        let mut vm = Vm::new(&mut debug_output);
+        vm.load(instructions);
+        vm.run().unwrap();
+
+        vm.into_registers()
+    }
+
+    #[test]
+    fn folds_constant_arithmetic_and_elides_superseded_loads() {
+        let optimized = optimize(instructions! {
+            alloc 3;
+            load 0 22;
+            load 1 100;
+            load 2 89;
+            call - 1 2;
+            call + 0 2;
+        });
+
+        assert_eq!(
+            optimized,
+            vec![
+                I::AllocRegisters { count: 3 },
+                I::LoadImmediate {
+                    dest: 0,
+                    value: Value::Integer(33),
+                },
+                I::LoadImmediate {
+                    dest: 1,
+                    value: Value::Integer(11),
+                },
+                I::LoadImmediate {
+                    dest: 2,
+                    value: Value::Integer(89),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn optimized_program_produces_same_registers_as_unoptimized() {
+        let program = instructions! {
+            alloc 3;
+            load 0 22;
+            load 1 100;
+            load 2 89;
+            call - 1 2;
+            call + 0 2;
+        };
+
+        assert_eq!(
+            run_into_registers(optimize(program.clone())),
+            run_into_registers(program)
+        );
+    }
+
+    #[test]
+    fn never_folds_an_effectful_builtin() {
+        let optimized = optimize(instructions! {
+            alloc 1;
+            load 0 "hi";
+            call debug 0 1;
+        });
+
+        assert!(optimized
+            .iter()
+            .any(|instruction| matches!(instruction, I::CallInternal { .. })));
+    }
+
+    #[test]
+    fn never_folds_a_call_with_a_non_constant_argument() {
+        let optimized = optimize(instructions! {
+            alloc 2;
+            load 0 1;
+            call + 0 2;
+        });
+
+        assert!(optimized
+            .iter()
+            .any(|instruction| matches!(instruction, I::CallInternal { .. })));
+    }
+
+    #[test]
+    fn preserves_branch_behavior_across_a_forward_jump() {
+        let program = instructions! {
+            alloc 3;
+            load 0 true;
+            jump_if 0 1;
+            load 1 1;
+
+            load 0 false;
+            jump_if 0 1;
+            load 2 2;
+        };
+
+        assert_eq!(
+            run_into_registers(optimize(program.clone())),
+            run_into_registers(program)
+        );
+    }
+
+    #[test]
+    fn preserves_loop_behavior_across_a_backward_jump() {
+        let program = instructions! {
+            alloc 4;
+            load 0 0;
+            load 1 1;
+            load 3 10;
+            call + 0 2;
+            copy 2 0;
+            call < 2 2;
+            jump_if 2 -4;
+        };
+
+        assert_eq!(
+            run_into_registers(optimize(program.clone())),
+            run_into_registers(program)
+        );
+    }
+}
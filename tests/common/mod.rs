@@ -5,7 +5,8 @@ use scarab::vm::Vm;
 pub fn exec(code: &str) -> String {
     let (program, positions) = parse_implicit_form_list(code.chars()).expect("parsing failed");
 
-    let instructions = compile(program, positions).expect("compilation failed");
+    let (instructions, argument_positions) =
+        compile(program, positions).expect("compilation failed");
     // eprintln!(
     //     "instructions: {}",
     //     instructions
@@ -15,12 +16,12 @@ pub fn exec(code: &str) -> String {
     //         .join("\n")
     // );
 
-    let mut debug_output = Vec::new();
+    let mut debug_output = String::new();
     {
         let mut vm = Vm::new(&mut debug_output);
-        vm.load(instructions);
+        vm.load_with_positions(instructions, argument_positions);
         vm.run().expect("running program failed");
     }
 
-    String::from_utf8(debug_output).unwrap().trim().to_string()
+    debug_output.trim().to_string()
 }